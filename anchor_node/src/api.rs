@@ -0,0 +1,162 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::Path,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use didanchor::InvalidUpdate;
+use identity_iota_client::document::ResolvedIotaDocument;
+
+use crate::{
+    dto::{
+        CommitReceiptResponse, CommitReceiptsResponse, CommitRequest, CommitStatus, ErrorResponse,
+        PendingChangesResponse, UpdateDocumentResponse,
+    },
+    state::{unix_timestamp, NodeState},
+};
+
+const OPENAPI_DOCUMENT: &str = include_str!("../openapi.json");
+
+/// `POST /documents`: Submits a signed document update.
+pub async fn update_document(
+    Extension(state): Extension<Arc<NodeState>>,
+    Json(document): Json<ResolvedIotaDocument>,
+) -> Result<Json<UpdateDocumentResponse>, ApiError> {
+    let did: String = document.document.id().to_string();
+
+    let mut anchor = state.anchor.lock().await;
+
+    anchor.update_document(document).await.map_err(|err| {
+        // Only an invalid update is the client's fault, storage or journal failures are ours.
+        if err.downcast_ref::<InvalidUpdate>().is_some() {
+            ApiError::new(StatusCode::BAD_REQUEST, format!("{err:#}"))
+        } else {
+            log::error!("failed to accept an update of {did}: {err:#}");
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+        }
+    })?;
+
+    Ok(Json(UpdateDocumentResponse {
+        did,
+        pending_changes: anchor.pending_count(),
+    }))
+}
+
+/// `GET /changes`: Lists the DIDs with pending changes.
+pub async fn pending_changes(
+    Extension(state): Extension<Arc<NodeState>>,
+) -> Json<PendingChangesResponse> {
+    let pending: Vec<String> = state
+        .anchor
+        .lock()
+        .await
+        .pending_dids()
+        .map(|did| did.to_string())
+        .collect();
+
+    Json(PendingChangesResponse { pending })
+}
+
+/// `POST /commits`: Commits the pending changes right away or after the requested delay.
+pub async fn commit(
+    Extension(state): Extension<Arc<NodeState>>,
+    request: Option<Json<CommitRequest>>,
+) -> Result<(StatusCode, Json<CommitReceiptResponse>), ApiError> {
+    let request: CommitRequest = request.map(|Json(request)| request).unwrap_or_default();
+
+    match request.delay_secs {
+        Some(delay_secs) => {
+            let scheduled_at: u64 = unix_timestamp().checked_add(delay_secs).ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("a delay of {delay_secs}s is too long"),
+                )
+            })?;
+            let receipt = state.new_commit(Some(scheduled_at));
+            let id: u64 = receipt.id;
+
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(delay_secs)).await;
+                state.run_commit(id).await;
+            });
+
+            Ok((StatusCode::ACCEPTED, Json(receipt)))
+        }
+        None => {
+            let id: u64 = state.new_commit(None).id;
+
+            let receipt: CommitReceiptResponse = state.run_commit(id).await.ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("commit {id} vanished"),
+                )
+            })?;
+
+            let status = if receipt.status == CommitStatus::Failed {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::OK
+            };
+
+            Ok((status, Json(receipt)))
+        }
+    }
+}
+
+/// `GET /commits`: Lists the receipts of all commits.
+pub async fn commit_receipts(
+    Extension(state): Extension<Arc<NodeState>>,
+) -> Json<CommitReceiptsResponse> {
+    Json(CommitReceiptsResponse {
+        commits: state.receipts(),
+    })
+}
+
+/// `GET /commits/{id}`: Returns the receipt of a commit.
+pub async fn commit_receipt(
+    Path(id): Path<u64>,
+    Extension(state): Extension<Arc<NodeState>>,
+) -> Result<Json<CommitReceiptResponse>, ApiError> {
+    state
+        .receipt(id)
+        .map(Json)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no commit with id {id}")))
+}
+
+/// `GET /openapi.json`: Returns the OpenAPI document describing this API.
+pub async fn openapi() -> Response {
+    (
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )],
+        OPENAPI_DOCUMENT,
+    )
+        .into_response()
+}
+
+/// An error response with a JSON body.
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: String) -> Self {
+        Self { status, message }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(ErrorResponse {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
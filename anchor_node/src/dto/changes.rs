@@ -0,0 +1,6 @@
+/// The response of `GET /changes`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PendingChangesResponse {
+    /// The DIDs whose updates have been accepted but not yet committed.
+    pub pending: Vec<String>,
+}
@@ -0,0 +1,108 @@
+use didanchor::CommitReceipt;
+
+/// The body of `POST /commits`.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct CommitRequest {
+    /// Delays the commit by the given number of seconds instead of committing right away.
+    #[serde(default)]
+    pub delay_secs: Option<u64>,
+}
+
+/// The receipt of a commit, returned by `POST /commits` and `GET /commits/{id}`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CommitReceiptResponse {
+    pub id: u64,
+    pub status: CommitStatus,
+    /// The unix timestamp at which the commit was requested.
+    pub requested_at: u64,
+    /// The unix timestamp at which the commit was scheduled to run, if it was delayed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled_for: Option<u64>,
+    /// The unix timestamp at which the commit finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    /// The alias id under which the changes were anchored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias_id: Option<String>,
+    /// The DIDs whose changes were anchored by the commit.
+    pub dids: Vec<String>,
+    /// Where and how the changes were anchored, if the commit anchored any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchored: Option<AnchoredCommitResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The details of a commit that anchored changes, taken from its [`CommitReceipt`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AnchoredCommitResponse {
+    /// The block, transaction and output that anchored the changes,
+    /// which are unknown if they were only found to be anchored after publishing reported an error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_id: Option<String>,
+    pub state_index: u32,
+    /// The amount of base tokens deposited in the Alias Output.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_deposit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_index_cid: Option<String>,
+    pub index_cid: String,
+    /// The prefix-hex encoded merkle root.
+    pub merkle_root: String,
+    pub chains: Vec<AnchoredChainResponse>,
+    pub prepare_millis: u64,
+    pub publish_millis: u64,
+}
+
+/// The chain of custody of a DID anchored by a commit.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AnchoredChainResponse {
+    pub did: String,
+    pub cid: String,
+    /// The prefix-hex encoded, packed merkle proof of the chain.
+    pub proof: String,
+}
+
+impl From<&CommitReceipt> for AnchoredCommitResponse {
+    fn from(receipt: &CommitReceipt) -> Self {
+        Self {
+            block_id: receipt.ledger.block_id.map(|id| id.to_string()),
+            transaction_id: receipt.ledger.transaction_id.map(|id| id.to_string()),
+            output_id: receipt.ledger.output_id.map(|id| id.to_string()),
+            state_index: receipt.ledger.state_index,
+            storage_deposit: receipt.ledger.storage_deposit,
+            previous_index_cid: receipt.previous_index_cid.clone(),
+            index_cid: receipt.index_cid.clone(),
+            merkle_root: prefix_hex::encode(receipt.merkle_root.clone()),
+            chains: receipt
+                .chains
+                .iter()
+                .map(|chain| AnchoredChainResponse {
+                    did: chain.did.to_string(),
+                    cid: chain.cid.clone(),
+                    proof: prefix_hex::encode(chain.proof.clone()),
+                })
+                .collect(),
+            prepare_millis: receipt.timing.prepare.as_millis() as u64,
+            publish_millis: receipt.timing.publish.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommitStatus {
+    Scheduled,
+    Committed,
+    Failed,
+}
+
+/// The response of `GET /commits`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CommitReceiptsResponse {
+    pub commits: Vec<CommitReceiptResponse>,
+}
@@ -0,0 +1,8 @@
+/// The response of `POST /documents`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UpdateDocumentResponse {
+    /// The DID of the accepted document.
+    pub did: String,
+    /// The number of DIDs with pending changes, including this one.
+    pub pending_changes: usize,
+}
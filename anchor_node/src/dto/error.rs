@@ -0,0 +1,5 @@
+/// The body of every error response.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
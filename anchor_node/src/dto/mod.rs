@@ -0,0 +1,9 @@
+mod changes;
+mod commit;
+mod document;
+mod error;
+
+pub use changes::*;
+pub use commit::*;
+pub use document::*;
+pub use error::*;
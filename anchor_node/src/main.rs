@@ -0,0 +1,126 @@
+mod api;
+mod dto;
+mod state;
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use axum::{
+    routing::{get, post},
+    Extension, Router,
+};
+use didanchor::{Anchor, CommitPolicy, Committer};
+
+use crate::state::NodeState;
+
+/// The address the node listens on.
+const LISTEN_ADDR_VAR: &str = "LISTEN_ADDR";
+/// Commit pending changes every given number of seconds.
+const COMMIT_INTERVAL_SECS_VAR: &str = "COMMIT_INTERVAL_SECS";
+/// Commit as soon as the given number of DIDs have pending changes.
+const COMMIT_MAX_PENDING_VAR: &str = "COMMIT_MAX_PENDING";
+/// Commit as soon as the oldest pending change is the given number of seconds old.
+const COMMIT_MAX_AGE_SECS_VAR: &str = "COMMIT_MAX_AGE_SECS";
+
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:8090";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+
+    let listen_addr: SocketAddr = std::env::var(LISTEN_ADDR_VAR)
+        .unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_owned())
+        .parse()
+        .with_context(|| format!("{LISTEN_ADDR_VAR} must be a socket address"))?;
+
+    let anchor: Anchor = Anchor::new().await?;
+    let state = Arc::new(NodeState::new(anchor));
+
+    let committer: Option<Committer> = commit_policy_from_env()?.map(|policy| {
+        log::info!("committing automatically with {policy:?}");
+        spawn_committer(&state, policy)
+    });
+
+    let app = Router::new()
+        .route("/documents", post(api::update_document))
+        .route("/changes", get(api::pending_changes))
+        .route("/commits", get(api::commit_receipts).post(api::commit))
+        .route("/commits/:id", get(api::commit_receipt))
+        .route("/openapi.json", get(api::openapi))
+        .layer(Extension(state));
+
+    log::info!("listening on {listen_addr}");
+
+    axum::Server::bind(&listen_addr)
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(async {
+            if let Err(err) = tokio::signal::ctrl_c().await {
+                log::error!("failed to listen for the shutdown signal: {err}");
+            }
+        })
+        .await?;
+
+    if let Some(committer) = committer {
+        log::info!("committing remaining changes before shutting down");
+        committer.shutdown().await?;
+    }
+
+    Ok(())
+}
+
+/// Spawns a [`Committer`] and records a receipt for every commit it makes.
+fn spawn_committer(state: &Arc<NodeState>, policy: CommitPolicy) -> Committer {
+    let committer = Committer::spawn(Arc::clone(&state.anchor), policy);
+    let mut anchored = committer.subscribe();
+    let state = Arc::clone(state);
+
+    tokio::spawn(async move {
+        loop {
+            match anchored.recv().await {
+                Ok(anchored) => {
+                    let receipt = state.record_anchored(anchored);
+                    log::info!(
+                        "commit {}: anchored {} change(s)",
+                        receipt.id,
+                        receipt.dids.len()
+                    );
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("missed the receipts of {skipped} commit(s)");
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    committer
+}
+
+/// Returns the commit policy configured through the environment,
+/// or `None` if changes should only be committed on request.
+fn commit_policy_from_env() -> anyhow::Result<Option<CommitPolicy>> {
+    let interval: Option<u64> = parse_env_var(COMMIT_INTERVAL_SECS_VAR)?;
+    let max_pending: Option<usize> = parse_env_var(COMMIT_MAX_PENDING_VAR)?;
+    let max_age: Option<u64> = parse_env_var(COMMIT_MAX_AGE_SECS_VAR)?;
+
+    if interval.is_none() && max_pending.is_none() && max_age.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(CommitPolicy {
+        interval: interval.map(Duration::from_secs),
+        max_pending,
+        max_age: max_age.map(Duration::from_secs),
+        ..CommitPolicy::default()
+    }))
+}
+
+fn parse_env_var<T: std::str::FromStr>(name: &str) -> anyhow::Result<Option<T>> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("{name} must be a non-negative integer")),
+        Err(_) => Ok(None),
+    }
+}
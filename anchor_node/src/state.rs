@@ -0,0 +1,138 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use didanchor::{Anchor, CommitReceipt};
+
+use crate::dto::{AnchoredCommitResponse, CommitReceiptResponse, CommitStatus};
+
+/// The state shared by all request handlers.
+pub struct NodeState {
+    pub anchor: Arc<tokio::sync::Mutex<Anchor>>,
+    receipts: Mutex<BTreeMap<u64, CommitReceiptResponse>>,
+    next_commit_id: AtomicU64,
+}
+
+impl NodeState {
+    pub fn new(anchor: Anchor) -> Self {
+        Self {
+            anchor: Arc::new(tokio::sync::Mutex::new(anchor)),
+            receipts: Mutex::new(BTreeMap::new()),
+            next_commit_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Records a new commit that is about to run or scheduled to run at `scheduled_for`.
+    pub fn new_commit(&self, scheduled_for: Option<u64>) -> CommitReceiptResponse {
+        let receipt = CommitReceiptResponse {
+            id: self.next_commit_id.fetch_add(1, Ordering::SeqCst),
+            status: CommitStatus::Scheduled,
+            requested_at: unix_timestamp(),
+            scheduled_for,
+            finished_at: None,
+            alias_id: None,
+            dids: Vec::new(),
+            anchored: None,
+            error: None,
+        };
+
+        self.update_receipt(receipt.clone());
+
+        receipt
+    }
+
+    /// Commits all pending changes and records the outcome in the receipt with the given `id`.
+    pub async fn run_commit(&self, id: u64) -> Option<CommitReceiptResponse> {
+        let mut receipt: CommitReceiptResponse = self.receipt(id)?;
+
+        let mut anchor = self.anchor.lock().await;
+
+        receipt.dids = anchor.pending_dids().map(|did| did.to_string()).collect();
+
+        if receipt.dids.is_empty() {
+            log::debug!("commit {id}: nothing to commit");
+            receipt.status = CommitStatus::Committed;
+        } else {
+            match anchor.commit_changes().await {
+                Ok(anchored) => {
+                    receipt.status = CommitStatus::Committed;
+                    receipt.alias_id = Some(anchored.alias_id().to_string());
+                    receipt.anchored = Some(AnchoredCommitResponse::from(&anchored));
+                }
+                Err(err) => {
+                    log::error!("commit {id} failed: {err:#}");
+                    receipt.status = CommitStatus::Failed;
+                    receipt.error = Some(format!("{err:#}"));
+                }
+            }
+        }
+
+        receipt.finished_at = Some(unix_timestamp());
+
+        self.update_receipt(receipt.clone());
+
+        Some(receipt)
+    }
+
+    /// Records a receipt for changes that were anchored by the background committer.
+    pub fn record_anchored(&self, anchored: CommitReceipt) -> CommitReceiptResponse {
+        let finished_at: u64 = anchored
+            .timing
+            .anchored_at
+            .duration_since(UNIX_EPOCH)
+            .expect("the system time should be after the unix epoch")
+            .as_secs();
+
+        let receipt = CommitReceiptResponse {
+            id: self.next_commit_id.fetch_add(1, Ordering::SeqCst),
+            status: CommitStatus::Committed,
+            requested_at: finished_at,
+            scheduled_for: None,
+            finished_at: Some(finished_at),
+            alias_id: Some(anchored.alias_id().to_string()),
+            dids: anchored.dids().iter().map(|did| did.to_string()).collect(),
+            anchored: Some(AnchoredCommitResponse::from(&anchored)),
+            error: None,
+        };
+
+        self.update_receipt(receipt.clone());
+
+        receipt
+    }
+
+    pub fn receipt(&self, id: u64) -> Option<CommitReceiptResponse> {
+        self.receipts
+            .lock()
+            .expect("the lock should not be poisoned")
+            .get(&id)
+            .cloned()
+    }
+
+    pub fn receipts(&self) -> Vec<CommitReceiptResponse> {
+        self.receipts
+            .lock()
+            .expect("the lock should not be poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn update_receipt(&self, receipt: CommitReceiptResponse) {
+        self.receipts
+            .lock()
+            .expect("the lock should not be poisoned")
+            .insert(receipt.id, receipt);
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the system time should be after the unix epoch")
+        .as_secs()
+}
@@ -1,6 +1,19 @@
+use cid::Cid;
+use identity_core::convert::FromJson;
+use multiaddr::{Multiaddr, Protocol};
+use packable::{
+    error::{UnpackError, UnpackErrorExt},
+    packer::Packer,
+    unpacker::{SliceUnpacker, Unpacker},
+    Packable, PackableExt,
+};
+
 use crate::IpfsNodePublicAddress;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// The prefix of alias content in the binary encoding, which distinguishes it from legacy JSON content.
+pub const ALIAS_CONTENT_MAGIC: [u8; 4] = *b"DIDA";
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct AliasContent {
     pub index_cid: String,
     pub ipfs_node_addrs: Vec<IpfsNodePublicAddress>,
@@ -19,4 +32,423 @@ impl AliasContent {
             merkle_root,
         }
     }
+
+    /// Encodes the content for the state metadata of an Alias Output with the [latest](AliasContentVersion::LATEST) version.
+    ///
+    /// The index CID is stored as bytes, the merkle root as its raw 32 bytes and the multiaddrs in binary form,
+    /// which keeps the storage deposit low. The CID is read back in its default string form,
+    /// i.e. base58 for CIDv0 and base32 for CIDv1.
+    pub fn to_state_metadata(&self) -> anyhow::Result<Vec<u8>> {
+        let mut state_metadata: Vec<u8> = ALIAS_CONTENT_MAGIC.to_vec();
+        state_metadata.push(AliasContentVersion::V1.number());
+        state_metadata.extend(AliasContentV1::try_from(self)?.pack_to_vec());
+
+        Ok(state_metadata)
+    }
+
+    /// Decodes content from the state metadata of an Alias Output in any known version
+    /// and returns it together with the version it was encoded with.
+    pub fn from_state_metadata(
+        state_metadata: &[u8],
+    ) -> anyhow::Result<(Self, AliasContentVersion)> {
+        let version: AliasContentVersion = AliasContentVersion::detect(state_metadata)?;
+
+        let content: AliasContent = match version {
+            AliasContentVersion::Legacy => AliasContent::from_json_slice(state_metadata)?,
+            AliasContentVersion::V1 => {
+                // Skips the magic prefix and the version.
+                let packed: &[u8] = &state_metadata[ALIAS_CONTENT_MAGIC.len() + 1..];
+                let mut unpacker = SliceUnpacker::new(packed);
+                let content: AliasContentV1 = AliasContentV1::unpack::<_, true>(&mut unpacker)
+                    .map_err(|err| anyhow::anyhow!("invalid alias content: {err:?}"))?;
+
+                // The encoding is canonical, so the content must span all of the state metadata.
+                if content.packed_len() != packed.len() {
+                    anyhow::bail!(
+                        "invalid alias content: {} trailing bytes",
+                        packed.len().saturating_sub(content.packed_len())
+                    );
+                }
+
+                AliasContent::try_from(content)?
+            }
+        };
+
+        Ok((content, version))
+    }
+}
+
+/// The version of the schema that alias content is encoded with.
+///
+/// Every version that was ever written stays readable: a new version gets a variant
+/// and a decoder that migrates its content into the current [`AliasContent`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum AliasContentVersion {
+    /// JSON without a version, which was written before the binary encoding.
+    Legacy,
+    /// The compact binary encoding.
+    V1,
+}
+
+impl AliasContentVersion {
+    /// The version that new content is written with.
+    pub const LATEST: Self = Self::V1;
+
+    /// Returns the number of the version, which follows the magic prefix in the binary encoding.
+    pub fn number(self) -> u8 {
+        match self {
+            AliasContentVersion::Legacy => 0,
+            AliasContentVersion::V1 => 1,
+        }
+    }
+
+    /// Detects the version that `state_metadata` is encoded with.
+    ///
+    /// Fails with [`UnsupportedAliasContentVersion`] if the content was written with a newer version
+    /// and with a different error if JSON content claims a known version, since only legacy content is JSON.
+    pub fn detect(state_metadata: &[u8]) -> anyhow::Result<Self> {
+        match state_metadata.strip_prefix(&ALIAS_CONTENT_MAGIC) {
+            Some(rest) => match rest.first() {
+                Some(1) => Ok(AliasContentVersion::V1),
+                Some(&version) => Err(UnsupportedAliasContentVersion { version }.into()),
+                None => anyhow::bail!("the alias content is missing its version"),
+            },
+            None => match Self::json_version(state_metadata)? {
+                None => Ok(AliasContentVersion::Legacy),
+                Some(version) if version > Self::LATEST.number() => {
+                    Err(UnsupportedAliasContentVersion { version }.into())
+                }
+                Some(version) => anyhow::bail!(
+                    "the alias content is JSON with version {version}, \
+                    but JSON content only exists in the legacy version"
+                ),
+            },
+        }
+    }
+
+    /// Returns the `version` field of JSON content, which legacy content doesn't have.
+    ///
+    /// Serde ignores unknown fields, so without this check, JSON content of a newer version
+    /// would be read as legacy content with its new fields silently dropped.
+    fn json_version(state_metadata: &[u8]) -> anyhow::Result<Option<u8>> {
+        let value: serde_json::Value = serde_json::from_slice(state_metadata).map_err(|err| {
+            anyhow::anyhow!("the alias content is neither binary nor JSON: {err}")
+        })?;
+
+        match value.get("version") {
+            None => Ok(None),
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u8::try_from(version).ok())
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("invalid alias content version {version}")),
+        }
+    }
+}
+
+/// Alias content was encoded with a version that this library doesn't know, i.e. by a newer one.
+///
+/// Returned wrapped in an [`anyhow::Error`] and can be obtained through [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedAliasContentVersion {
+    pub version: u8,
+}
+
+impl std::fmt::Display for UnsupportedAliasContentVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "alias content version {} is not supported, the latest supported version is {}",
+            self.version,
+            AliasContentVersion::LATEST.number()
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedAliasContentVersion {}
+
+/// The binary encoding of [`AliasContent`] in version 1, whose fields are validated before packing.
+///
+/// Every variable-length field is prefixed by its length as a `u8`.
+struct AliasContentV1 {
+    index_cid: Vec<u8>,
+    /// The host, swarm port, gateway port and peer id of each node as binary multiaddrs.
+    ipfs_node_addrs: Vec<[Vec<u8>; 4]>,
+    merkle_root: [u8; 32],
+}
+
+impl TryFrom<&AliasContent> for AliasContentV1 {
+    type Error = anyhow::Error;
+
+    fn try_from(content: &AliasContent) -> Result<Self, Self::Error> {
+        let index_cid: Vec<u8> = Cid::try_from(content.index_cid.as_str())?.to_bytes();
+
+        let merkle_root: [u8; 32] = content.merkle_root.as_slice().try_into().map_err(|_| {
+            anyhow::anyhow!(
+                "the merkle root must be 32 bytes long, but is {}",
+                content.merkle_root.len()
+            )
+        })?;
+
+        let ipfs_node_addrs: Vec<[Vec<u8>; 4]> = content
+            .ipfs_node_addrs
+            .iter()
+            .map(|addr| {
+                [
+                    Multiaddr::from(addr.host.clone()).to_vec(),
+                    addr.swarm_port.to_vec(),
+                    Multiaddr::from(addr.gateway_port.clone()).to_vec(),
+                    Multiaddr::from(addr.peer_id.clone()).to_vec(),
+                ]
+            })
+            .collect();
+
+        if u8::try_from(ipfs_node_addrs.len()).is_err() {
+            anyhow::bail!("at most {} ipfs node addresses can be published", u8::MAX);
+        }
+
+        for field in std::iter::once(&index_cid).chain(ipfs_node_addrs.iter().flatten()) {
+            if u8::try_from(field.len()).is_err() {
+                anyhow::bail!(
+                    "a field of {} bytes exceeds the maximum length",
+                    field.len()
+                );
+            }
+        }
+
+        Ok(Self {
+            index_cid,
+            ipfs_node_addrs,
+            merkle_root,
+        })
+    }
+}
+
+impl TryFrom<AliasContentV1> for AliasContent {
+    type Error = anyhow::Error;
+
+    fn try_from(packed: AliasContentV1) -> Result<Self, Self::Error> {
+        let index_cid: String = Cid::try_from(packed.index_cid.as_slice())?.to_string();
+
+        let ipfs_node_addrs = packed
+            .ipfs_node_addrs
+            .into_iter()
+            .map(|[host, swarm_port, gateway_port, peer_id]| {
+                Ok(IpfsNodePublicAddress {
+                    host: single_protocol(host)?,
+                    swarm_port: Multiaddr::try_from(swarm_port)?,
+                    gateway_port: single_protocol(gateway_port)?,
+                    peer_id: single_protocol(peer_id)?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<IpfsNodePublicAddress>>>()?;
+
+        Ok(AliasContent::new(
+            index_cid,
+            ipfs_node_addrs,
+            packed.merkle_root.to_vec(),
+        ))
+    }
+}
+
+/// Decodes a binary multiaddr that consists of exactly one protocol.
+fn single_protocol(bytes: Vec<u8>) -> anyhow::Result<Protocol<'static>> {
+    let multiaddr: Multiaddr = Multiaddr::try_from(bytes)?;
+    let mut protocols = multiaddr.iter();
+
+    match (protocols.next(), protocols.next()) {
+        (Some(protocol), None) => Ok(protocol.acquire()),
+        _ => anyhow::bail!("expected a single protocol in {multiaddr}"),
+    }
+}
+
+impl Packable for AliasContentV1 {
+    type UnpackError = anyhow::Error;
+
+    fn pack<P: Packer>(&self, packer: &mut P) -> Result<(), P::Error> {
+        pack_prefixed(&self.index_cid, packer)?;
+        self.merkle_root.pack(packer)?;
+
+        (self.ipfs_node_addrs.len() as u8).pack(packer)?;
+
+        for fields in self.ipfs_node_addrs.iter() {
+            for field in fields {
+                pack_prefixed(field, packer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unpack<U: Unpacker, const VERIFY: bool>(
+        unpacker: &mut U,
+    ) -> Result<Self, UnpackError<Self::UnpackError, U::Error>> {
+        let index_cid: Vec<u8> = unpack_prefixed::<_, VERIFY>(unpacker)?;
+        let merkle_root: [u8; 32] = <[u8; 32]>::unpack::<_, VERIFY>(unpacker).coerce()?;
+
+        let count: u8 = u8::unpack::<_, VERIFY>(unpacker).coerce()?;
+        let mut ipfs_node_addrs: Vec<[Vec<u8>; 4]> = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            ipfs_node_addrs.push([
+                unpack_prefixed::<_, VERIFY>(unpacker)?,
+                unpack_prefixed::<_, VERIFY>(unpacker)?,
+                unpack_prefixed::<_, VERIFY>(unpacker)?,
+                unpack_prefixed::<_, VERIFY>(unpacker)?,
+            ]);
+        }
+
+        Ok(Self {
+            index_cid,
+            ipfs_node_addrs,
+            merkle_root,
+        })
+    }
+}
+
+fn pack_prefixed<P: Packer>(bytes: &[u8], packer: &mut P) -> Result<(), P::Error> {
+    (bytes.len() as u8).pack(packer)?;
+    packer.pack_bytes(bytes)
+}
+
+fn unpack_prefixed<U: Unpacker, const VERIFY: bool>(
+    unpacker: &mut U,
+) -> Result<Vec<u8>, UnpackError<anyhow::Error, U::Error>> {
+    let len: usize = u8::unpack::<_, VERIFY>(unpacker).coerce()? as usize;
+
+    let mut bytes = vec![0; len];
+    unpacker.unpack_bytes(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use identity_core::convert::ToJson;
+    use multiaddr::{Multiaddr, Protocol};
+
+    use super::{
+        AliasContent, AliasContentVersion, UnsupportedAliasContentVersion, ALIAS_CONTENT_MAGIC,
+    };
+    use crate::IpfsNodePublicAddress;
+
+    // The content of the golden files in `tests/golden`, one per version.
+    // Their bytes must never change, since content in these versions was published to the ledger.
+    fn content() -> AliasContent {
+        let ipfs_node_addr = IpfsNodePublicAddress {
+            host: Protocol::Dns("ipfs.example.com".into()),
+            swarm_port: "/udp/4001/quic".parse::<Multiaddr>().unwrap(),
+            gateway_port: Protocol::Tcp(8080),
+            peer_id: "/p2p/12D3KooWL3EovpbdH1Axsk51xv9ascEsv9a81BuQdSZyNDtRSaHu"
+                .parse::<Multiaddr>()
+                .unwrap()
+                .pop()
+                .unwrap(),
+        };
+
+        AliasContent::new(
+            "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn".to_owned(),
+            vec![ipfs_node_addr],
+            vec![7; 32],
+        )
+    }
+
+    fn golden_v1() -> Vec<u8> {
+        prefix_hex::decode(include_str!("../tests/golden/alias_content_v1.hex").trim()).unwrap()
+    }
+
+    fn unsupported_version(state_metadata: &[u8]) -> Option<u8> {
+        AliasContentVersion::detect(state_metadata)
+            .unwrap_err()
+            .downcast_ref::<UnsupportedAliasContentVersion>()
+            .map(|err| err.version)
+    }
+
+    #[test]
+    fn test_golden_files() {
+        let legacy: &[u8] = include_bytes!("../tests/golden/alias_content_legacy.json");
+        assert_eq!(
+            AliasContentVersion::detect(legacy).unwrap(),
+            AliasContentVersion::Legacy
+        );
+        assert_eq!(
+            AliasContent::from_state_metadata(legacy).unwrap(),
+            (content(), AliasContentVersion::Legacy)
+        );
+
+        let v1: Vec<u8> = golden_v1();
+        assert_eq!(
+            AliasContentVersion::detect(&v1).unwrap(),
+            AliasContentVersion::V1
+        );
+        assert_eq!(
+            AliasContent::from_state_metadata(&v1).unwrap(),
+            (content(), AliasContentVersion::V1)
+        );
+
+        // The latest version must be written exactly like its golden file.
+        assert_eq!(AliasContentVersion::LATEST, AliasContentVersion::V1);
+        assert_eq!(content().to_state_metadata().unwrap(), v1);
+    }
+
+    #[test]
+    fn test_state_metadata_is_smaller_than_json() {
+        let binary: Vec<u8> = content().to_state_metadata().unwrap();
+        let json: Vec<u8> = content().to_json_vec().unwrap();
+
+        assert!(binary.starts_with(&ALIAS_CONTENT_MAGIC));
+        assert!(binary.len() < json.len());
+    }
+
+    #[test]
+    fn test_unknown_versions_are_rejected() {
+        let mut binary: Vec<u8> = golden_v1();
+        binary[ALIAS_CONTENT_MAGIC.len()] = 2;
+        assert_eq!(unsupported_version(&binary), Some(2));
+        assert!(AliasContent::from_state_metadata(&binary).is_err());
+
+        let mut json: serde_json::Value =
+            serde_json::from_slice(include_bytes!("../tests/golden/alias_content_legacy.json"))
+                .unwrap();
+        json["version"] = 2.into();
+        assert_eq!(
+            unsupported_version(&serde_json::to_vec(&json).unwrap()),
+            Some(2)
+        );
+
+        // Known versions are binary, so JSON that claims one of them is invalid rather than unsupported.
+        json["version"] = 1.into();
+        assert_eq!(
+            unsupported_version(&serde_json::to_vec(&json).unwrap()),
+            None
+        );
+
+        assert!(AliasContent::from_state_metadata(&ALIAS_CONTENT_MAGIC).is_err());
+        assert!(AliasContent::from_state_metadata(b"not alias content").is_err());
+    }
+
+    #[test]
+    fn test_trailing_bytes_are_rejected() {
+        let mut binary: Vec<u8> = golden_v1();
+        binary.push(0);
+
+        assert!(AliasContent::from_state_metadata(&binary).is_err());
+    }
+
+    #[test]
+    fn test_invalid_content_is_not_encoded() {
+        let short_root = AliasContent {
+            merkle_root: vec![0; 31],
+            ..content()
+        };
+        assert!(short_root.to_state_metadata().is_err());
+
+        let invalid_cid = AliasContent {
+            index_cid: "not a cid".to_owned(),
+            ..content()
+        };
+        assert!(invalid_cid.to_state_metadata().is_err());
+    }
 }
@@ -0,0 +1,245 @@
+use iota_client::{
+    api_types::responses::OutputResponse,
+    block::{
+        input::Input,
+        output::{AliasId, AliasOutput, Output, OutputId},
+        payload::{
+            transaction::{TransactionEssence, TransactionId},
+            Payload,
+        },
+        Block,
+    },
+    Client,
+};
+
+use crate::{resolve_alias_output_response, AliasContent, AliasContentVersion};
+
+/// A state of an alias together with the milestone that confirmed it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HistoricalAliasState {
+    pub state_index: u32,
+    pub content: AliasContent,
+    /// The version that the content was encoded with in the state metadata.
+    pub version: AliasContentVersion,
+    /// The first output that held the state.
+    pub output_id: OutputId,
+    /// The index of the milestone that confirmed the state.
+    pub milestone_index: u32,
+    /// The Unix timestamp of the milestone that confirmed the state, in seconds.
+    pub milestone_timestamp: u32,
+}
+
+/// Walks back through the states of an alias by following the transactions that created its outputs,
+/// each of which consumed the previous output.
+///
+/// The current output is looked up through the node's indexer. Nodes prune spent outputs and old blocks
+/// after a while, so a long history requires an archive node, which is asked for whatever the node no longer has.
+#[derive(Debug, Clone)]
+pub struct AliasHistory {
+    client: Client,
+    archive: Option<Client>,
+}
+
+impl AliasHistory {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            archive: None,
+        }
+    }
+
+    /// Falls back to the archive node at `archive_endpoint` for outputs and blocks that were pruned.
+    pub fn with_archive_node(mut self, archive_endpoint: &str) -> anyhow::Result<Self> {
+        self.archive = Some(
+            Client::builder()
+                .with_primary_node(archive_endpoint, None)?
+                .finish()?,
+        );
+
+        Ok(self)
+    }
+
+    /// Returns the states of the alias from the oldest to the current one,
+    /// or an empty list if the alias doesn't exist.
+    pub async fn states(&self, alias_id: AliasId) -> anyhow::Result<Vec<HistoricalAliasState>> {
+        self.states_since(alias_id, 0).await
+    }
+
+    /// Returns the states of the alias from `state_index` to the current one,
+    /// without walking back further than necessary.
+    pub async fn states_since(
+        &self,
+        alias_id: AliasId,
+        state_index: u32,
+    ) -> anyhow::Result<Vec<HistoricalAliasState>> {
+        let mut history: Vec<HistoricalAliasState> = Vec::new();
+
+        if alias_id.is_null() {
+            return Ok(history);
+        }
+
+        let (mut output_id, mut output_response) =
+            match resolve_alias_output_response(&self.client, alias_id).await? {
+                Some(output) => output,
+                None => return Ok(history),
+            };
+
+        loop {
+            let alias_output: AliasOutput = alias_output(&output_id, &output_response)?;
+
+            if alias_output.state_index() < state_index {
+                break;
+            }
+
+            let (content, version): (AliasContent, AliasContentVersion) =
+                AliasContent::from_state_metadata(alias_output.state_metadata())?;
+
+            push_state(
+                &mut history,
+                HistoricalAliasState {
+                    state_index: alias_output.state_index(),
+                    content,
+                    version,
+                    output_id,
+                    milestone_index: output_response.metadata.milestone_index_booked,
+                    milestone_timestamp: output_response.metadata.milestone_timestamp_booked,
+                },
+            );
+
+            match self
+                .previous_output(output_id.transaction_id(), alias_id)
+                .await?
+            {
+                Some(previous) => (output_id, output_response) = previous,
+                None => break,
+            }
+        }
+
+        history.reverse();
+
+        Ok(history)
+    }
+
+    /// Returns the output of `alias_id` consumed by the transaction with `transaction_id`,
+    /// or `None` if the transaction created the alias.
+    async fn previous_output(
+        &self,
+        transaction_id: &TransactionId,
+        alias_id: AliasId,
+    ) -> anyhow::Result<Option<(OutputId, OutputResponse)>> {
+        let block: Block = self.included_block(transaction_id).await?;
+
+        let inputs: Vec<OutputId> = match block.payload() {
+            Some(Payload::Transaction(tx_payload)) => {
+                let TransactionEssence::Regular(regular) = tx_payload.essence();
+
+                regular
+                    .inputs()
+                    .iter()
+                    .filter_map(|input| match input {
+                        Input::Utxo(utxo_input) => Some(*utxo_input.output_id()),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            _ => anyhow::bail!("block of transaction {transaction_id} contains no transaction"),
+        };
+
+        for output_id in inputs {
+            let output_response: OutputResponse = self.output(&output_id).await?;
+
+            if let Output::Alias(alias_output) = Output::try_from(&output_response.output)? {
+                let input_alias_id: AliasId = if alias_output.alias_id().is_null() {
+                    AliasId::from(output_id)
+                } else {
+                    alias_output.alias_id().to_owned()
+                };
+
+                if input_alias_id == alias_id {
+                    return Ok(Some((output_id, output_response)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn output(&self, output_id: &OutputId) -> anyhow::Result<OutputResponse> {
+        match (self.client.get_output(output_id).await, &self.archive) {
+            (Ok(output_response), _) => Ok(output_response),
+            (Err(err), Some(archive)) => {
+                log::debug!("asking the archive node for output {output_id}: {err}");
+                Ok(archive.get_output(output_id).await?)
+            }
+            (Err(err), None) => Err(err.into()),
+        }
+    }
+
+    async fn included_block(&self, transaction_id: &TransactionId) -> anyhow::Result<Block> {
+        match (
+            self.client.get_included_block(transaction_id).await,
+            &self.archive,
+        ) {
+            (Ok(block), _) => Ok(block),
+            (Err(err), Some(archive)) => {
+                log::debug!(
+                    "asking the archive node for the block of transaction {transaction_id}: {err}"
+                );
+                Ok(archive.get_included_block(transaction_id).await?)
+            }
+            (Err(err), None) => Err(err.into()),
+        }
+    }
+}
+
+fn alias_output(
+    output_id: &OutputId,
+    output_response: &OutputResponse,
+) -> anyhow::Result<AliasOutput> {
+    match Output::try_from(&output_response.output)? {
+        Output::Alias(alias_output) => Ok(alias_output),
+        _ => anyhow::bail!("output {output_id} is not an alias output"),
+    }
+}
+
+/// Adds `state` to the `history` that is walked back from the current state.
+///
+/// Governance transitions keep the state index and content,
+/// so a state was confirmed with the oldest output that holds it.
+fn push_state(history: &mut Vec<HistoricalAliasState>, state: HistoricalAliasState) {
+    match history.last_mut() {
+        Some(last) if last.state_index == state.state_index => *last = state,
+        _ => history.push(state),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iota_client::block::{output::OutputId, payload::transaction::TransactionId};
+
+    use super::{push_state, HistoricalAliasState};
+    use crate::{AliasContent, AliasContentVersion};
+
+    fn state(state_index: u32, milestone_index: u32) -> HistoricalAliasState {
+        HistoricalAliasState {
+            state_index,
+            content: AliasContent::new("cid".to_owned(), Vec::new(), vec![0; 32]),
+            version: AliasContentVersion::LATEST,
+            output_id: OutputId::new(TransactionId::new([milestone_index as u8; 32]), 0).unwrap(),
+            milestone_index,
+            milestone_timestamp: milestone_index * 10,
+        }
+    }
+
+    #[test]
+    fn test_governance_transitions_keep_the_confirming_milestone() {
+        let mut history: Vec<HistoricalAliasState> = Vec::new();
+
+        // Walking back from the current output, which was created by a governance transition.
+        for (state_index, milestone_index) in [(1, 5), (1, 3), (0, 2), (0, 1)] {
+            push_state(&mut history, state(state_index, milestone_index));
+        }
+
+        assert_eq!(history, vec![state(1, 3), state(0, 1)]);
+    }
+}
@@ -1,81 +1,236 @@
 use did_common::{ChainOfCustody, VerifiableChainOfCustody};
-use std::{collections::HashMap, time::Instant};
-use url::Url;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::Context;
+use futures::{StreamExt, TryStreamExt};
 use identity_iota_client::document::ResolvedIotaDocument;
 use identity_iota_core::did::IotaDID;
 use iota_client::block::output::AliasId;
 use merkle_tree::Proof;
+use packable::PackableExt;
 
 use crate::{
-    resolve_alias_content, AliasContent, AnchorConfig, AnchorOutput, ChainStorage, DIDIndex,
-    MerkleDIDs,
+    AliasContent, AnchorConfig, AnchoredChain, ChainStorage, CommitReceipt, CommitTiming, DIDIndex,
+    ExternalSigner, Journal, JournalRecord, JournalRecovery, JournaledUpdate, LedgerAnchor,
+    LedgerReceipt, MerkleDIDs, PartialAdd, PinGarbageCollector, ValidatedUpdate,
 };
 
 pub struct Anchor {
     storage: ChainStorage,
     merkle: MerkleDIDs,
     uncommitted_chains: HashMap<IotaDID, ChainOfCustody>,
+    // When the oldest uncommitted update of each DID was accepted.
+    pending_since: HashMap<IotaDID, SystemTime>,
     index: DIDIndex,
     config: AnchorConfig,
-    anchor_output: AnchorOutput,
+    // Where the config is written back to once the alias id changes, if anywhere.
+    config_path: Option<PathBuf>,
+    ledger: Box<dyn LedgerAnchor>,
     index_cid: Option<String>,
+    journal: Journal,
+    pins: PinGarbageCollector,
 }
 
 impl Anchor {
+    /// Creates an anchor from the config at [`AnchorConfig::DEFAULT_PATH`].
     pub async fn new() -> anyhow::Result<Self> {
-        let config: AnchorConfig = AnchorConfig::read_default_location().await?;
+        Self::from_config(AnchorConfig::read_default_location().await?).await
+    }
+
+    /// Creates an anchor from the given `config`, which is written to [`AnchorConfig::DEFAULT_PATH`]
+    /// whenever the alias id changes.
+    pub async fn from_config(config: AnchorConfig) -> anyhow::Result<Self> {
+        let storage: ChainStorage = config.chain_storage().await?;
+        let ledger: Box<dyn LedgerAnchor> = config.ledger_anchor().await?;
+
+        Self::init(
+            config,
+            storage,
+            ledger,
+            Some(PathBuf::from(AnchorConfig::DEFAULT_PATH)),
+        )
+        .await
+    }
+
+    /// Creates an anchor from the given `config`, whose Alias Outputs are controlled by the external `signer`.
+    ///
+    /// The config must refer to the signer through [`SecretSource::External`](crate::SecretSource::External)
+    /// and is written to [`AnchorConfig::DEFAULT_PATH`] whenever the alias id changes.
+    pub async fn with_signer(
+        config: AnchorConfig,
+        signer: impl ExternalSigner + 'static,
+    ) -> anyhow::Result<Self> {
+        let storage: ChainStorage = config.chain_storage().await?;
+        let ledger: Box<dyn LedgerAnchor> = config.ledger_anchor_with_signer(signer).await?;
+
+        Self::init(
+            config,
+            storage,
+            ledger,
+            Some(PathBuf::from(AnchorConfig::DEFAULT_PATH)),
+        )
+        .await
+    }
+
+    /// Creates an anchor from the given `config` that publishes to the given `storage` and `ledger`
+    /// instead of the configured ones, e.g. to test it without a network.
+    ///
+    /// The config is not written back, the current alias id can be obtained from [`Anchor::alias_id`].
+    pub async fn from_parts(
+        config: AnchorConfig,
+        storage: ChainStorage,
+        ledger: impl LedgerAnchor + 'static,
+    ) -> anyhow::Result<Self> {
+        Self::init(config, storage, Box::new(ledger), None).await
+    }
 
-        let anchor_output: AnchorOutput = AnchorOutput::new(
-            config.mnemonic.clone(),
-            config.alias_id,
-            &config.iota_endpoint,
-        )?;
+    async fn init(
+        mut config: AnchorConfig,
+        storage: ChainStorage,
+        ledger: Box<dyn LedgerAnchor>,
+        config_path: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let mut journal: Journal = Journal::open(&config.journal_path).await?;
+
+        // The process might have stopped after publishing the first Alias Output
+        // but before writing its id to the config.
+        if let Some(alias_id) = journal.published_alias_id() {
+            if config.alias_id != alias_id {
+                config.alias_id = alias_id;
+
+                if let Some(ref config_path) = config_path {
+                    config.write(config_path).await?;
+                }
+            }
+        }
 
         // Retrieve the current alias output to obtain the latest index cid.
         // We could store this locally, but this way seems safer overall.
-        let content: Option<AliasContent> =
-            resolve_alias_content(&anchor_output.client, config.alias_id).await?;
+        let content: Option<AliasContent> = ledger.read_content(config.alias_id).await?;
 
-        let storage = ChainStorage::new(
-            config
-                .ipfs_node_management_addrs
-                .iter()
-                .map(|addr| addr.to_cluster_address().unwrap())
-                .collect::<Vec<Url>>(),
-            config
-                .ipfs_node_management_addrs
-                .iter()
-                .map(|addr| addr.to_api_address().unwrap())
-                .collect::<Vec<Url>>(),
-        )?;
-
-        let (index, index_cid): (DIDIndex, Option<String>) = if let Some(content) = content {
-            (
-                storage.get_index(&content.index_cid).await?,
-                Some(content.index_cid),
-            )
-        } else {
-            (DIDIndex::new(), None)
-        };
+        let (index, index_cid, merkle): (DIDIndex, Option<String>, MerkleDIDs) =
+            if let Some(content) = content {
+                let index: DIDIndex = storage.get_index(&content.index_cid).await?;
+                let merkle: MerkleDIDs =
+                    Self::rebuild_merkle_tree(&storage, &index, &content.merkle_root)
+                        .await
+                        .context("failed to rebuild the merkle tree of the anchored DIDs")?;
+
+                (index, Some(content.index_cid), merkle)
+            } else {
+                (DIDIndex::new(), None, MerkleDIDs::new())
+            };
 
-        Ok(Self {
+        let recovery: JournalRecovery = journal.recover(index_cid.as_deref(), &index);
+
+        for cid in recovery.orphaned.iter() {
+            if let Err(err) = storage.unpin(cid).await {
+                log::warn!("failed to unpin {cid} left over by an interrupted commit: {err:#}");
+            }
+        }
+
+        let mut pins: PinGarbageCollector =
+            PinGarbageCollector::load(&config.pin_gc_path, config.retention).await?;
+
+        let references: Vec<String> = index.values().cloned().chain(index_cid.clone()).collect();
+        pins.record(recovery.superseded, &references).await?;
+        pins.collect(&storage).await?;
+
+        // Compacting first makes the recovery idempotent, should the replay be interrupted.
+        journal.compact(&recovery.pending).await?;
+
+        let mut anchor = Self {
             storage,
-            merkle: MerkleDIDs::new(),
+            merkle,
             uncommitted_chains: HashMap::new(),
+            pending_since: HashMap::new(),
             index,
             config,
-            anchor_output,
+            config_path,
+            ledger,
             index_cid,
-        })
+            journal,
+            pins,
+        };
+
+        if !recovery.pending.is_empty() {
+            log::debug!("replaying {} journaled update(s)", recovery.pending.len());
+        }
+
+        for update in recovery.pending {
+            let validated: ValidatedUpdate = anchor
+                .validate_update(update.document)
+                .await
+                .context("failed to replay a journaled update")?;
+
+            anchor.apply_update(validated, update.accepted_at);
+        }
+
+        Ok(anchor)
+    }
+
+    /// Rebuilds the merkle tree from the chains of custody in the anchored `index`,
+    /// so that the next commit's root covers every anchored DID and not only the ones updated since.
+    async fn rebuild_merkle_tree(
+        storage: &ChainStorage,
+        index: &DIDIndex,
+        merkle_root: &[u8],
+    ) -> anyhow::Result<MerkleDIDs> {
+        log::debug!("rebuilding the merkle tree of {} DID(s)", index.len());
+
+        let chains: Vec<(IotaDID, usize, ChainOfCustody)> = futures::stream::iter(index.keys())
+            .map(|did| async move {
+                let vcoc: VerifiableChainOfCustody = storage
+                    .get(did, index)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("no chain of custody found for {did}"))?;
+
+                Ok::<_, anyhow::Error>((did.clone(), vcoc.proof.index(), vcoc.chain_of_custody))
+            })
+            .buffer_unordered(ChainStorage::GET_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let merkle: MerkleDIDs = MerkleDIDs::from_chains(chains)?;
+
+        if merkle.merkle_root() != merkle_root {
+            anyhow::bail!(
+                "the chains of custody in the index don't reproduce the anchored merkle root"
+            );
+        }
+
+        Ok(merkle)
     }
 
     pub async fn update_document(&mut self, document: ResolvedIotaDocument) -> anyhow::Result<()> {
+        let accepted_at: SystemTime = SystemTime::now();
+        let validated: ValidatedUpdate = self.validate_update(document.clone()).await?;
+
+        // The update is only accepted once it is durable, so it is journaled before it is applied.
+        self.journal
+            .append(&JournalRecord::Update(JournaledUpdate {
+                document,
+                accepted_at,
+            }))
+            .await?;
+        self.apply_update(validated, accepted_at);
+
+        Ok(())
+    }
+
+    /// Validates `document` against the current chain of custody of its DID without modifying the anchor's state.
+    async fn validate_update(
+        &self,
+        document: ResolvedIotaDocument,
+    ) -> anyhow::Result<ValidatedUpdate> {
         let did = document.document.id().to_owned();
 
-        let chain_of_custody: Option<ChainOfCustody> = match self.uncommitted_chains.remove(&did) {
-            coc @ Some(_) => coc,
+        let chain_of_custody: Option<ChainOfCustody> = match self.uncommitted_chains.get(&did) {
+            Some(coc) => Some(coc.clone()),
             None => self
                 .storage
                 .get(&did, &self.index)
@@ -83,68 +238,451 @@ impl Anchor {
                 .map(|vcoc| vcoc.chain_of_custody),
         };
 
-        let chain_of_custody: ChainOfCustody =
-            self.merkle.update_document(chain_of_custody, document)?;
+        self.merkle
+            .validate_update(chain_of_custody, document)
+            .map_err(|err| {
+                InvalidUpdate {
+                    did,
+                    reason: format!("{err:#}"),
+                }
+                .into()
+            })
+    }
+
+    fn apply_update(&mut self, update: ValidatedUpdate, accepted_at: SystemTime) {
+        let did: IotaDID = update.did().to_owned();
+        let chain_of_custody: ChainOfCustody = self.merkle.apply_update(update);
 
+        self.pending_since.entry(did.clone()).or_insert(accepted_at);
         self.uncommitted_chains.insert(did, chain_of_custody);
+    }
 
-        Ok(())
+    /// Returns the id of the alias the changes are anchored in, which is null before the first commit.
+    pub fn alias_id(&self) -> AliasId {
+        self.config.alias_id
+    }
+
+    /// Returns the DIDs whose updates have been accepted but not yet committed.
+    pub fn pending_changes(&self) -> Vec<IotaDID> {
+        self.uncommitted_chains.keys().cloned().collect()
+    }
+
+    /// Iterates over the DIDs whose updates have been accepted but not yet committed, without copying them.
+    pub fn pending_dids(&self) -> impl Iterator<Item = &IotaDID> {
+        self.uncommitted_chains.keys()
     }
 
-    pub async fn commit_changes(&mut self) -> anyhow::Result<AliasId> {
+    /// Returns the number of DIDs with changes that have not yet been committed.
+    pub fn pending_count(&self) -> usize {
+        self.uncommitted_chains.len()
+    }
+
+    /// Returns when the oldest update that has not yet been committed was accepted.
+    pub fn oldest_pending_change(&self) -> Option<SystemTime> {
+        self.pending_since.values().min().copied()
+    }
+
+    /// Commits all pending changes.
+    ///
+    /// The commit is prepared by pinning the new chains of custody and index,
+    /// and then published in a new Alias Output. The anchor's state is only updated once the output is included.
+    /// If the commit fails before that, the new pins are removed again and the changes remain pending,
+    /// so that the commit can be retried.
+    ///
+    /// The returned receipt records where the changes were anchored and the proofs of the committed chains.
+    pub async fn commit_changes(&mut self) -> anyhow::Result<CommitReceipt> {
+        let started_at = SystemTime::now();
         let time = Instant::now();
         let changes_to_commit = self.uncommitted_chains.len();
 
-        let mut uncommitted_chains = HashMap::new();
+        self.journal.append(&JournalRecord::CommitStarted).await?;
 
-        std::mem::swap(&mut self.uncommitted_chains, &mut uncommitted_chains);
+        let mut pinned: Vec<String> = Vec::new();
 
-        for (did, coc) in uncommitted_chains.into_iter() {
-            let proof: Proof<_> = self
-                .merkle
-                .generate_merkle_proof(&did)
-                .context("should be contained in the tree")?;
+        let (prepared, ledger, prepare): (PreparedCommit, LedgerReceipt, Duration) =
+            match self.prepare_and_publish(&mut pinned).await {
+                Ok(commit) => commit,
+                Err(err) => {
+                    self.roll_back(pinned).await;
+                    return Err(err);
+                }
+            };
 
-            // Store the proof together with the COC in storage.
-            let vcoc = VerifiableChainOfCustody::new(proof, coc);
-            let content_id: String = self.storage.add(&vcoc).await?;
+        let timing = CommitTiming {
+            started_at,
+            anchored_at: SystemTime::now(),
+            prepare,
+            publish: time.elapsed().saturating_sub(prepare),
+        };
+        let alias_id: AliasId = ledger.alias_id;
+
+        // The commit is anchored at this point, so the state is updated regardless.
+        // Without this record, recovery can still tell from the ledger that the commit was anchored.
+        if let Err(err) = self
+            .journal
+            .append(&JournalRecord::CommitPublished { alias_id })
+            .await
+        {
+            log::error!("failed to journal the published commit: {err:#}");
+        }
 
-            if let Some(cid) = self.index.get(&did) {
-                // Remove the previous pin as we no longer need it.
-                // In a production deployment, this would probably have to be done later
-                // to ensure availability within a certain grace period.
-                self.storage.unpin(cid).await?;
+        let receipt = CommitReceipt {
+            ledger,
+            previous_index_cid: self.index_cid.take(),
+            index_cid: prepared.index_cid.clone(),
+            merkle_root: prepared.content.merkle_root.clone(),
+            chains: prepared.chains,
+            timing,
+        };
+
+        self.index = prepared.index;
+        self.index_cid = Some(prepared.index_cid);
+        self.uncommitted_chains.clear();
+        self.pending_since.clear();
+        self.config.alias_id = alias_id;
+
+        // Superseded content is only unpinned after the retention period,
+        // so that resolvers holding a previous state of the alias can still resolve it.
+        // Failing to do so only leaves content pinned for longer, so the commit still succeeds.
+        if let Err(err) = self.pins.record(prepared.superseded, &pinned).await {
+            log::error!("failed to record the content superseded by the commit: {err:#}");
+        }
+        if let Err(err) = self.collect_garbage().await {
+            log::warn!("failed to unpin superseded content: {err:#}");
+        }
+
+        let config_written: bool = match self.config_path {
+            Some(ref config_path) => {
+                match self.config.write(config_path).await {
+                    Ok(()) => true,
+                    Err(err) => {
+                        log::error!("the changes were anchored, but the config could not be updated: {err:#}");
+                        false
+                    }
+                }
             }
+            None => true,
+        };
 
-            // Update the storage index.
-            self.index.insert(did, content_id);
+        // Updates can't be accepted during a commit, so none are left pending.
+        // An uncompacted journal is recovered from like an interrupted commit, so the commit still succeeds.
+        // It is kept if the config is outdated, since the alias id is then only recorded in the journal.
+        if config_written {
+            if let Err(err) = self.journal.compact(&[]).await {
+                log::error!("failed to compact the journal: {err:#}");
+            }
         }
 
-        // Unpin old index and upload and set new one.
+        log::debug!(
+            "committed {changes_to_commit} change(s) in {}s",
+            time.elapsed().as_secs()
+        );
+
+        Ok(receipt)
+    }
+
+    /// Unpins the superseded content whose retention period has passed.
+    pub async fn collect_garbage(&mut self) -> anyhow::Result<usize> {
+        self.pins.collect(&self.storage).await
+    }
+
+    async fn prepare_and_publish(
+        &mut self,
+        pinned: &mut Vec<String>,
+    ) -> anyhow::Result<(PreparedCommit, LedgerReceipt, Duration)> {
+        let time = Instant::now();
+        let prepared: PreparedCommit = self.prepare_commit(pinned).await?;
+        let prepare: Duration = time.elapsed();
+
+        let receipt: LedgerReceipt = self.publish_commit(&prepared).await?;
+
+        Ok((prepared, receipt, prepare))
+    }
+
+    /// Pins the pending chains of custody and the resulting index without modifying the anchor's state.
+    /// Every pin is recorded in `pinned` as soon as it was added.
+    async fn prepare_commit(&mut self, pinned: &mut Vec<String>) -> anyhow::Result<PreparedCommit> {
+        let mut index: DIDIndex = self.index.clone();
+        let mut superseded: Vec<String> = Vec::new();
+
+        let mut dids: Vec<IotaDID> = Vec::with_capacity(self.uncommitted_chains.len());
+        let mut proofs: Vec<Vec<u8>> = Vec::with_capacity(self.uncommitted_chains.len());
+
+        let mut chains: Vec<VerifiableChainOfCustody> = self
+            .uncommitted_chains
+            .iter()
+            .map(|(did, coc)| {
+                let proof: Proof<_> = self
+                    .merkle
+                    .generate_merkle_proof(did)
+                    .context("should be contained in the tree")?;
+
+                dids.push(did.clone());
+                // Kept packed for the receipt, since the proof is moved into the chain.
+                proofs.push(proof.pack_to_vec());
+
+                // Store the proof together with the COC in storage.
+                Ok(VerifiableChainOfCustody::new(proof, coc.clone()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        // Only the chains of the updated DIDs are listed in the receipt.
+        let updated: usize = dids.len();
+
+        for (did, vcoc) in self.refresh_proofs().await? {
+            dids.push(did);
+            chains.push(vcoc);
+        }
+
+        let storage: &ChainStorage = &self.storage;
+        let mut batches = futures::stream::iter(chains.chunks(ChainStorage::ADD_BATCH_SIZE))
+            .map(|batch| storage.add_many(batch))
+            .buffered(ChainStorage::ADD_CONCURRENCY);
+
+        let mut content_ids: Vec<String> = Vec::with_capacity(dids.len());
+        let mut error: Option<anyhow::Error> = None;
+
+        // Every batch is awaited even after a failure, so that all pins are known for the rollback.
+        while let Some(result) = batches.next().await {
+            let added: &[String] = match result {
+                Ok(ref cids) => cids,
+                // A batch that failed might have pinned some of its content nonetheless.
+                Err(ref err) => err
+                    .downcast_ref::<PartialAdd>()
+                    .map(|partial| partial.added.as_slice())
+                    .unwrap_or_default(),
+            };
+
+            pinned.extend(added.iter().cloned());
+
+            let records: Vec<JournalRecord> = added
+                .iter()
+                .map(|cid| JournalRecord::Pinned { cid: cid.clone() })
+                .collect();
+
+            if let Err(err) = self.journal.append_all(&records).await {
+                error.get_or_insert(err);
+            }
+
+            match result {
+                Ok(cids) => content_ids.extend(cids),
+                Err(err) => {
+                    error.get_or_insert(err);
+                }
+            }
+        }
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        let mut anchored_chains: Vec<AnchoredChain> = Vec::with_capacity(dids.len());
+
+        for (position, (did, cid)) in dids.into_iter().zip(content_ids).enumerate() {
+            if let Some(superseded_cid) = index.insert(did.clone(), cid.clone()) {
+                superseded.push(superseded_cid);
+            }
+
+            if position < updated {
+                anchored_chains.push(AnchoredChain {
+                    did,
+                    cid,
+                    proof: proofs[position].clone(),
+                });
+            }
+        }
+
+        let index_cid: String = self.storage.publish_index(&index).await?;
+        pinned.push(index_cid.clone());
+        self.journal
+            .append(&JournalRecord::Pinned {
+                cid: index_cid.clone(),
+            })
+            .await?;
+
         if let Some(ref old_index_cid) = self.index_cid {
-            self.storage.unpin(old_index_cid).await?;
+            superseded.push(old_index_cid.clone());
         }
-        let index_cid: String = self.storage.publish_index(&self.index).await?;
-        self.index_cid = Some(index_cid.clone());
 
-        // Update the Alias Output.
+        // Unchanged content, e.g. the index of an empty commit, is still referenced.
+        let referenced: HashSet<&String> = pinned.iter().collect();
+        superseded.retain(|cid| !referenced.contains(cid));
+
         let content = AliasContent::new(
-            index_cid,
+            index_cid.clone(),
             self.config.ipfs_node_public_addrs.clone(),
             self.merkle.merkle_root(),
         );
 
-        let alias_id = self.anchor_output.publish_output(content).await?;
+        Ok(PreparedCommit {
+            index,
+            index_cid,
+            content,
+            superseded,
+            chains: anchored_chains,
+        })
+    }
 
-        self.config.alias_id = alias_id;
+    /// Returns the anchored chains of custody that were not updated, with a proof against the
+    /// current merkle root in place of their stored one, if that changed.
+    ///
+    /// Every update changes the root, so without this the stored proofs of all other DIDs would no
+    /// longer verify against the root that is anchored next.
+    async fn refresh_proofs(&self) -> anyhow::Result<Vec<(IotaDID, VerifiableChainOfCustody)>> {
+        if self.uncommitted_chains.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let storage: &ChainStorage = &self.storage;
+        let index: &DIDIndex = &self.index;
 
-        self.config.write_default_location().await?;
+        let chains: Vec<(IotaDID, VerifiableChainOfCustody)> = futures::stream::iter(
+            index
+                .keys()
+                .filter(|did| !self.uncommitted_chains.contains_key(*did)),
+        )
+        .map(|did| async move {
+            let vcoc: VerifiableChainOfCustody = storage
+                .get(did, index)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("no chain of custody found for {did}"))?;
+
+            Ok::<_, anyhow::Error>((did.clone(), vcoc))
+        })
+        .buffer_unordered(ChainStorage::GET_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+        let mut refreshed: Vec<(IotaDID, VerifiableChainOfCustody)> = Vec::new();
+
+        for (did, vcoc) in chains {
+            let proof: Proof<_> = self
+                .merkle
+                .generate_merkle_proof(&did)
+                .context("should be contained in the tree")?;
+
+            if proof.pack_to_vec() != vcoc.proof.pack_to_vec() {
+                refreshed.push((
+                    did,
+                    VerifiableChainOfCustody::new(proof, vcoc.chain_of_custody),
+                ));
+            }
+        }
 
         log::debug!(
-            "committed {changes_to_commit} change(s) in {}s",
-            time.elapsed().as_secs()
+            "refreshed the proofs of {} unchanged DID(s)",
+            refreshed.len()
         );
 
-        Ok(alias_id)
+        Ok(refreshed)
     }
+
+    /// Publishes the prepared commit in a new Alias Output and waits for it to be included.
+    async fn publish_commit(&mut self, prepared: &PreparedCommit) -> anyhow::Result<LedgerReceipt> {
+        self.journal
+            .append(&JournalRecord::IndexPublished {
+                index_cid: prepared.index_cid.clone(),
+                superseded: prepared.superseded.clone(),
+            })
+            .await?;
+
+        match self
+            .ledger
+            .publish_content(self.config.alias_id, prepared.content.clone())
+            .await
+        {
+            Ok(receipt) => Ok(receipt),
+            // The block might have been included even though waiting for it failed.
+            Err(err) => match self.anchored_state_index(&prepared.index_cid).await {
+                Some(state_index) => {
+                    log::warn!(
+                        "publishing reported an error, but the output was included: {err:#}"
+                    );
+                    Ok(LedgerReceipt::new(self.config.alias_id, state_index))
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Returns the index of the current alias state if it references the index with `index_cid`.
+    async fn anchored_state_index(&self, index_cid: &str) -> Option<u32> {
+        if self.config.alias_id.is_null() {
+            return None;
+        }
+
+        let anchored = async {
+            match self.ledger.read_content(self.config.alias_id).await? {
+                Some(content) if content.index_cid == index_cid => {
+                    self.ledger.state_index(self.config.alias_id).await
+                }
+                _ => Ok(None),
+            }
+        };
+
+        match anchored.await {
+            Ok(state_index) => state_index,
+            Err(err) => {
+                log::warn!("failed to check whether the commit was anchored: {err:#}");
+                None
+            }
+        }
+    }
+
+    /// Unpins the content that was `pinned` by a failed commit.
+    async fn roll_back(&mut self, pinned: Vec<String>) {
+        log::debug!("rolling back commit");
+
+        // Content identical to what is already anchored or still retained after it was superseded
+        // must stay pinned.
+        let referenced: HashSet<&str> = self
+            .index
+            .values()
+            .chain(self.index_cid.as_ref())
+            .map(String::as_str)
+            .chain(self.pins.superseded().iter().map(|pin| pin.cid.as_str()))
+            .collect();
+
+        for cid in pinned {
+            if referenced.contains(cid.as_str()) {
+                continue;
+            }
+
+            if let Err(err) = self.storage.unpin(&cid).await {
+                log::warn!("failed to unpin {cid} of the failed commit: {err:#}");
+            }
+        }
+
+        if let Err(err) = self.journal.append(&JournalRecord::CommitAborted).await {
+            log::error!("failed to journal the aborted commit: {err:#}");
+        }
+    }
+}
+
+/// A commit whose content is pinned, but not yet anchored.
+struct PreparedCommit {
+    index: DIDIndex,
+    index_cid: String,
+    content: AliasContent,
+    /// Content that is no longer referenced once the commit is anchored.
+    superseded: Vec<String>,
+    chains: Vec<AnchoredChain>,
+}
+
+/// An update was rejected because it is not a valid addition to the chain of custody of its DID.
+///
+/// Returned wrapped in an [`anyhow::Error`] and can be obtained through [`anyhow::Error::downcast_ref`],
+/// which tells it apart from failures of the storage or the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidUpdate {
+    pub did: IotaDID,
+    pub reason: String,
 }
+
+impl std::fmt::Display for InvalidUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid update of {}: {}", self.did, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidUpdate {}
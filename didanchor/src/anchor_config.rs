@@ -1,20 +1,66 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use iota_client::block::output::AliasId;
+use iota_client::{
+    block::{address::Address, output::AliasId},
+    secret::{mnemonic::MnemonicSecretManager, SecretManager},
+};
 
-use crate::{IpfsNodeManagementAddress, IpfsNodePublicAddress};
+use url::Url;
+
+use crate::{
+    AnchorOutput, ChainStorage, ExternalSigner, FileLedger, FilesystemStore,
+    IpfsNodeManagementAddress, IpfsNodePublicAddress, KuboStore, LedgerAnchor, MemoryStore,
+    MockLedger, RetentionPolicy, SecretSource,
+};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AnchorConfig {
     pub alias_id: AliasId,
-    pub mnemonic: String,
+    /// The secret that controls the Alias Output.
+    #[serde(default)]
+    pub secret: SecretSource,
+    /// A plaintext mnemonic, which is only still read from existing configs and takes precedence over the `secret`.
+    /// Move it to the location the `secret` refers to instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<String>,
+    /// The bech32 address that governs the Alias Output, e.g. of a cold key held offline.
+    /// Defaults to the address of the `secret`, which always controls the state.
+    /// It only takes effect when the Alias Output is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub governor_address: Option<String>,
     pub iota_endpoint: String,
     pub ipfs_node_public_addrs: Vec<IpfsNodePublicAddress>,
     pub ipfs_node_management_addrs: Vec<IpfsNodeManagementAddress>,
+    /// The path of the [`Journal`](crate::Journal) holding uncommitted updates.
+    #[serde(default = "AnchorConfig::default_journal_path")]
+    pub journal_path: PathBuf,
+    /// The path of the record of superseded content that is still pinned.
+    #[serde(default = "AnchorConfig::default_pin_gc_path")]
+    pub pin_gc_path: PathBuf,
+    /// How long superseded content stays pinned.
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// Where chains of custody and indices are stored.
+    #[serde(default)]
+    pub content_store: ContentStoreConfig,
+    /// Where the alias content is anchored.
+    #[serde(default)]
+    pub ledger: LedgerConfig,
 }
 
 impl AnchorConfig {
     pub const DEFAULT_PATH: &'static str = "./anchor_config.toml";
+    pub const DEFAULT_JOURNAL_PATH: &'static str = "./anchor_journal.jsonl";
+
+    pub const DEFAULT_PIN_GC_PATH: &'static str = "./anchor_pins.json";
+
+    pub fn default_journal_path() -> PathBuf {
+        PathBuf::from(Self::DEFAULT_JOURNAL_PATH)
+    }
+
+    pub fn default_pin_gc_path() -> PathBuf {
+        PathBuf::from(Self::DEFAULT_PIN_GC_PATH)
+    }
 
     pub async fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
         log::debug!("reading config from {}", path.as_ref().display());
@@ -31,6 +77,88 @@ impl AnchorConfig {
         Ok(())
     }
 
+    /// Creates the [`ChainStorage`] backed by the configured content store.
+    pub async fn chain_storage(&self) -> anyhow::Result<ChainStorage> {
+        Ok(match &self.content_store {
+            ContentStoreConfig::IpfsCluster => ChainStorage::new(
+                self.ipfs_node_management_addrs
+                    .iter()
+                    .map(|addr| addr.to_cluster_address())
+                    .collect::<anyhow::Result<Vec<Url>>>()?,
+                self.ipfs_node_management_addrs
+                    .iter()
+                    .map(|addr| addr.to_api_address())
+                    .collect::<anyhow::Result<Vec<Url>>>()?,
+            )?,
+            ContentStoreConfig::Kubo { api_addr } => {
+                ChainStorage::with_store(KuboStore::new(Url::parse(api_addr)?)?)
+            }
+            ContentStoreConfig::Filesystem { path } => {
+                ChainStorage::with_store(FilesystemStore::new(path).await?)
+            }
+            ContentStoreConfig::Memory => ChainStorage::with_store(MemoryStore::new()),
+        })
+    }
+
+    /// Creates the [`LedgerAnchor`] for the configured ledger.
+    pub async fn ledger_anchor(&self) -> anyhow::Result<Box<dyn LedgerAnchor>> {
+        Ok(match &self.ledger {
+            LedgerConfig::Iota => Box::new(self.governed(AnchorOutput::new(
+                self.secret_manager().await?,
+                self.alias_id,
+                &self.iota_endpoint,
+            )?)?),
+            LedgerConfig::File { path } => Box::new(FileLedger::open(path).await?),
+            LedgerConfig::Memory => Box::new(MockLedger::new()),
+        })
+    }
+
+    /// Creates the [`LedgerAnchor`] for the configured ledger, which signs with the external `signer`
+    /// if it is an IOTA network.
+    pub async fn ledger_anchor_with_signer(
+        &self,
+        signer: impl ExternalSigner + 'static,
+    ) -> anyhow::Result<Box<dyn LedgerAnchor>> {
+        if self.secret != SecretSource::External {
+            anyhow::bail!("the config does not refer to an external signer");
+        }
+
+        match &self.ledger {
+            LedgerConfig::Iota => Ok(Box::new(self.governed(AnchorOutput::with_signer(
+                signer,
+                self.alias_id,
+                &self.iota_endpoint,
+            )?)?)),
+            _ => self.ledger_anchor().await,
+        }
+    }
+
+    /// Sets the configured governor on `output`, if any.
+    fn governed(&self, output: AnchorOutput) -> anyhow::Result<AnchorOutput> {
+        Ok(match self.governor_address {
+            Some(ref governor_address) => {
+                let (_, governor): (String, Address) =
+                    Address::try_from_bech32(governor_address)
+                        .map_err(|err| anyhow::anyhow!("invalid governor address: {err}"))?;
+
+                output.with_governor(governor)
+            }
+            None => output,
+        })
+    }
+
+    async fn secret_manager(&self) -> anyhow::Result<SecretManager> {
+        if let Some(ref mnemonic) = self.mnemonic {
+            log::warn!("the config contains a plaintext mnemonic, refer to it through the `secret` instead");
+
+            return Ok(SecretManager::Mnemonic(
+                MnemonicSecretManager::try_from_mnemonic(mnemonic)?,
+            ));
+        }
+
+        self.secret.secret_manager().await
+    }
+
     pub async fn read_default_location() -> anyhow::Result<Self> {
         Self::read(Self::DEFAULT_PATH).await
     }
@@ -39,3 +167,31 @@ impl AnchorConfig {
         self.write(Self::DEFAULT_PATH).await
     }
 }
+
+/// The content store an anchor publishes to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentStoreConfig {
+    /// The IPFS cluster given by the `ipfs_node_management_addrs`.
+    #[default]
+    IpfsCluster,
+    /// A single IPFS (Kubo) node with the given RPC API address, e.g. `http://127.0.0.1:5001`.
+    Kubo { api_addr: String },
+    /// A directory on the local filesystem.
+    Filesystem { path: PathBuf },
+    /// Memory, which is lost when the anchor stops.
+    Memory,
+}
+
+/// The ledger an anchor publishes its alias content to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LedgerConfig {
+    /// The IOTA network of the `iota_endpoint`, using Alias Outputs controlled by the `secret`.
+    #[default]
+    Iota,
+    /// A JSON file on the local filesystem.
+    File { path: PathBuf },
+    /// Memory, which is lost when the anchor stops.
+    Memory,
+}
@@ -1,7 +1,10 @@
-use identity_core::convert::ToJson;
+use std::sync::Arc;
+
 use iota_client::{
+    api::PreparedTransactionData,
     block::{
         address::Address,
+        input::UtxoInput,
         output::{
             unlock_condition::{
                 GovernorAddressUnlockCondition, StateControllerAddressUnlockCondition,
@@ -9,43 +12,122 @@ use iota_client::{
             AliasId, AliasOutput, AliasOutputBuilder, Output, OutputId, RentStructure,
             UnlockCondition,
         },
-        payload::{transaction::TransactionEssence, Payload},
+        payload::{
+            transaction::{TransactionEssence, TransactionPayload},
+            Payload,
+        },
+        signature::{Ed25519Signature, Signature},
+        unlock::{ReferenceUnlock, SignatureUnlock, Unlock, Unlocks},
         Block,
     },
-    secret::{mnemonic::MnemonicSecretManager, SecretManager},
+    secret::SecretManager,
     Client,
 };
 
-use crate::{resolve_alias_output, AliasContent};
+use crate::{resolve_alias_output, AliasContent, ExternalSigner, LedgerReceipt};
 
 #[derive(Debug)]
 pub struct AnchorOutput {
     pub(crate) client: Client,
     pub(crate) alias_id: AliasId,
-    // insecure.
-    secret_manager: SecretManager,
+    signer: Option<Signer>,
+    // The governor of new Alias Outputs, which defaults to the signer's address.
+    governor: Option<Address>,
 }
 
-impl AnchorOutput {
-    pub fn new(mnemonic: String, alias_id: AliasId, iota_endpoint: &str) -> anyhow::Result<Self> {
-        let client: Client = Client::builder()
-            .with_primary_node(iota_endpoint, None)?
-            .finish()?;
+/// Signs the transactions that publish new Alias Outputs.
+enum Signer {
+    SecretManager(SecretManager),
+    External(Arc<dyn ExternalSigner>),
+}
 
-        let secret_manager: SecretManager =
-            SecretManager::Mnemonic(MnemonicSecretManager::try_from_mnemonic(&mnemonic)?);
+// Not derived, so that secrets don't end up in logs.
+impl std::fmt::Debug for Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Signer::SecretManager(_) => f.write_str("SecretManager"),
+            Signer::External(_) => f.write_str("External"),
+        }
+    }
+}
 
+impl AnchorOutput {
+    /// Creates an output that publishes content with transactions signed by the `secret_manager`.
+    pub fn new(
+        secret_manager: SecretManager,
+        alias_id: AliasId,
+        iota_endpoint: &str,
+    ) -> anyhow::Result<Self> {
         Ok(AnchorOutput {
-            client,
+            client: Self::client(iota_endpoint)?,
             alias_id,
-            secret_manager,
+            signer: Some(Signer::SecretManager(secret_manager)),
+            governor: None,
         })
     }
 
-    pub async fn publish_output(&mut self, content: AliasContent) -> anyhow::Result<AliasId> {
+    /// Creates an output that publishes content with transactions signed by the external `signer`.
+    pub fn with_signer(
+        signer: impl ExternalSigner + 'static,
+        alias_id: AliasId,
+        iota_endpoint: &str,
+    ) -> anyhow::Result<Self> {
+        Ok(AnchorOutput {
+            client: Self::client(iota_endpoint)?,
+            alias_id,
+            signer: Some(Signer::External(Arc::new(signer))),
+            governor: None,
+        })
+    }
+
+    /// Creates an output that can only be used to read alias content, e.g. by a resolver.
+    pub fn read_only(client: Client) -> Self {
+        AnchorOutput {
+            client,
+            alias_id: AliasId::null(),
+            signer: None,
+            governor: None,
+        }
+    }
+
+    /// Sets the governor of the Alias Output created by the first publish, e.g. a cold key held offline.
+    ///
+    /// The signer only controls the state of the output then, while governance transitions,
+    /// such as [`AnchorOutput::rotate_state_controller`], must be signed by the governor.
+    pub fn with_governor(mut self, governor: Address) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
+    fn client(iota_endpoint: &str) -> anyhow::Result<Client> {
+        Ok(Client::builder()
+            .with_primary_node(iota_endpoint, None)?
+            .finish()?)
+    }
+
+    fn signer(&self) -> anyhow::Result<&Signer> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("a read-only output cannot publish content"))
+    }
+
+    /// Returns the address that controls the state of new Alias Outputs.
+    pub(crate) async fn address(&self) -> anyhow::Result<Address> {
+        match self.signer()? {
+            Signer::SecretManager(secret_manager) => Ok(self
+                .client
+                .get_addresses(secret_manager)
+                .with_range(0..1)
+                .get_raw()
+                .await?[0]),
+            Signer::External(signer) => signer.address().await,
+        }
+    }
+
+    pub async fn publish_output(&mut self, content: AliasContent) -> anyhow::Result<LedgerReceipt> {
         log::debug!("publishing new Alias Output");
 
-        let content_vec = content.to_json_vec()?;
+        let content_vec = content.to_state_metadata()?;
 
         let rent_structure = self.client.get_rent_structure().await?;
 
@@ -56,42 +138,46 @@ impl AnchorOutput {
                 .await?
         };
 
-        let block: Block = self
-            .client
-            .block()
-            .with_secret_manager(&self.secret_manager)
-            .with_outputs(vec![alias_output.into()])?
-            .finish()
-            .await?;
+        // Fails before building the block if the deposit can't be covered.
+        let (inputs, alias_output): (Vec<UtxoInput>, AliasOutput) =
+            self.select_inputs(alias_output, rent_structure).await?;
+
+        let block: Block = match self.signer()? {
+            Signer::SecretManager(secret_manager) => {
+                self.client
+                    .block()
+                    .with_secret_manager(secret_manager)
+                    .with_inputs(inputs)?
+                    .with_outputs(vec![alias_output.into()])?
+                    .finish()
+                    .await?
+            }
+            Signer::External(signer) => {
+                self.sign_externally(signer.as_ref(), inputs, alias_output)
+                    .await?
+            }
+        };
 
         let _ = self
             .client
             .retry_until_included(&block.id(), None, None)
             .await?;
 
-        let alias_id = Self::alias_ids_from_block(&block)?
-            .into_iter()
-            .next()
-            .expect("there should be exactly one alias id");
+        let receipt: LedgerReceipt = Self::receipt_from_block(&block)?;
 
-        log::debug!("published output with id {alias_id}");
+        log::debug!("published output with id {}", receipt.alias_id);
 
-        self.alias_id = alias_id;
+        self.alias_id = receipt.alias_id;
 
-        Ok(alias_id)
+        Ok(receipt)
     }
 
-    async fn new_output(
+    pub(crate) async fn new_output(
         &self,
         state_metadata: Vec<u8>,
         rent_structure: RentStructure,
     ) -> anyhow::Result<AliasOutput> {
-        let address: Address = self
-            .client
-            .get_addresses(&self.secret_manager)
-            .with_range(0..1)
-            .get_raw()
-            .await?[0];
+        let address: Address = self.address().await?;
 
         Ok(
             AliasOutputBuilder::new_with_minimum_storage_deposit(rent_structure, AliasId::null())?
@@ -103,13 +189,13 @@ impl AnchorOutput {
                     StateControllerAddressUnlockCondition::new(address),
                 ))
                 .add_unlock_condition(UnlockCondition::GovernorAddress(
-                    GovernorAddressUnlockCondition::new(address),
+                    GovernorAddressUnlockCondition::new(self.governor.unwrap_or(address)),
                 ))
                 .finish()?,
         )
     }
 
-    async fn update_output(
+    pub(crate) async fn update_output(
         &self,
         state_metadata: Vec<u8>,
         rent_structure: RentStructure,
@@ -131,26 +217,70 @@ impl AnchorOutput {
         Ok(alias_output_builder.finish()?)
     }
 
-    /// Returns all DID documents of the Alias Outputs contained in the payload's transaction, if any.
-    fn alias_ids_from_block(block: &Block) -> anyhow::Result<Vec<AliasId>> {
-        let mut documents = Vec::new();
+    /// Builds and signs the transaction that publishes `alias_output` from the `inputs` with the external `signer`.
+    async fn sign_externally(
+        &self,
+        signer: &dyn ExternalSigner,
+        inputs: Vec<UtxoInput>,
+        alias_output: AliasOutput,
+    ) -> anyhow::Result<Block> {
+        let prepared: PreparedTransactionData = self
+            .client
+            .block()
+            .with_inputs(inputs)?
+            .with_outputs(vec![alias_output.into()])?
+            .prepare_transaction()
+            .await?;
+
+        let signature = Ed25519Signature::new(
+            signer.public_key().await?,
+            signer.sign(&prepared.essence.hash()).await?,
+        );
+
+        // Every input is controlled by the signer's address, so one signature unlocks all of them.
+        let mut unlocks: Vec<Unlock> = vec![Unlock::Signature(SignatureUnlock::new(
+            Signature::Ed25519(signature),
+        ))];
+        for _ in 1..prepared.inputs_data.len() {
+            unlocks.push(Unlock::Reference(ReferenceUnlock::new(0)?));
+        }
 
+        let payload = TransactionPayload::new(prepared.essence, Unlocks::new(unlocks)?)?;
+
+        Ok(self
+            .client
+            .block()
+            .finish_block(Some(Payload::Transaction(Box::new(payload))))
+            .await?)
+    }
+
+    /// Returns the receipt of the Alias Output contained in the payload's transaction.
+    fn receipt_from_block(block: &Block) -> anyhow::Result<LedgerReceipt> {
         if let Some(Payload::Transaction(tx_payload)) = block.payload() {
             let TransactionEssence::Regular(regular) = tx_payload.essence();
 
             for (index, output) in regular.outputs().iter().enumerate() {
                 if let Output::Alias(alias_output) = output {
+                    let output_id = OutputId::new(tx_payload.id(), index.try_into()?)?;
+
                     let alias_id = if alias_output.alias_id().is_null() {
-                        AliasId::from(OutputId::new(tx_payload.id(), index.try_into()?)?)
+                        AliasId::from(output_id)
                     } else {
                         alias_output.alias_id().to_owned()
                     };
 
-                    documents.push(alias_id);
+                    return Ok(LedgerReceipt {
+                        alias_id,
+                        state_index: alias_output.state_index(),
+                        block_id: Some(block.id()),
+                        transaction_id: Some(tx_payload.id()),
+                        output_id: Some(output_id),
+                        storage_deposit: Some(alias_output.amount()),
+                    });
                 }
             }
         }
 
-        Ok(documents)
+        anyhow::bail!("block {} contains no alias output", block.id())
     }
 }
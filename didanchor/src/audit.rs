@@ -0,0 +1,464 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use bytes::Bytes;
+use crypto::hashes::{blake2b::Blake2b256, Digest, Output};
+use did_common::VerifiableChainOfCustody;
+use futures::StreamExt;
+use identity_core::convert::FromJson;
+use identity_iota_core::did::IotaDID;
+use iota_client::{block::output::AliasId, Client as IotaClient};
+use merkle_tree::{DigestExt, MerkleTree};
+use packable::{unpacker::SliceUnpacker, Packable};
+use url::Url;
+
+use crate::{
+    AliasContent, AnchorOutput, ContentStore, DIDIndex, KuboStore, LedgerAnchor, ResolutionError,
+};
+
+/// The result of auditing every DID anchored by a publisher.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    pub alias_id: AliasId,
+    pub state_index: u32,
+    pub index_cid: String,
+    #[serde(serialize_with = "crate::evidence::prefix_hex_bytes::serialize")]
+    pub merkle_root: Vec<u8>,
+    /// The number of DIDs in the index.
+    pub dids: usize,
+    /// The number of chains of custody that passed every check.
+    pub verified: usize,
+    pub root: RootCheck,
+    pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    /// Returns whether every chain of custody is valid and the chains reproduce the merkle root.
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty() && self.root == RootCheck::Reproduced
+    }
+}
+
+/// Whether the leaves of all chains of custody reproduce the anchored merkle root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RootCheck {
+    Reproduced,
+    /// The leaves are not exactly one per position of the tree or compute a different root.
+    Mismatch,
+    /// Not every chain of custody could be read, so the root could not be recomputed.
+    Incomplete,
+}
+
+/// A problem found by an [`Auditor`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AuditFinding {
+    /// The chain of custody could not be fetched.
+    MissingCid {
+        did: IotaDID,
+        cid: String,
+        reason: String,
+    },
+    /// The chain of custody could not be unpacked or is not a valid integration chain.
+    InvalidChain {
+        did: IotaDID,
+        cid: String,
+        reason: String,
+    },
+    /// The leaves of all chains don't reproduce the merkle root and the stored proof of the chain of custody
+    /// doesn't verify against it either.
+    BadProof {
+        did: IotaDID,
+        cid: String,
+        leaf_index: usize,
+    },
+    /// The index maps `did` to the chain of custody of `chain_did`.
+    MismatchedDid {
+        did: IotaDID,
+        cid: String,
+        chain_did: IotaDID,
+    },
+    /// Multiple DIDs are mapped to the same chain of custody.
+    DuplicateCid { cid: String, dids: Vec<IotaDID> },
+    /// The proofs of multiple DIDs claim the same position in the tree.
+    DuplicateLeaf {
+        leaf_index: usize,
+        dids: Vec<IotaDID>,
+    },
+}
+
+/// How far an audit has progressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditProgress {
+    /// The number of chains of custody that were checked so far.
+    pub checked: usize,
+    pub total: usize,
+}
+
+/// Audits all DIDs anchored by a publisher at once, rather than resolving them one by one.
+///
+/// Every chain of custody referenced by the current index is downloaded and checked like the [`Resolver`](crate::Resolver)
+/// would, and all findings are collected instead of failing on the first one. Additionally, the leaves of
+/// all chains are checked to reproduce the merkle root, i.e. that the index accounts for every anchored DID.
+pub struct Auditor {
+    ledger: Arc<dyn LedgerAnchor>,
+    content_store: Arc<dyn ContentStore>,
+    concurrency_limit: usize,
+}
+
+impl Auditor {
+    /// The default number of chains of custody that are fetched concurrently.
+    pub const DEFAULT_CONCURRENCY_LIMIT: usize = 16;
+
+    pub fn new(iota_endpoint: &str, ipfs_endpoint: &str) -> anyhow::Result<Self> {
+        let client: IotaClient = IotaClient::builder()
+            .with_primary_node(iota_endpoint, None)?
+            .finish()?;
+
+        let ipfs_endpoint: Url = ipfs_endpoint
+            .parse()
+            .context("unable to parse IPFS endpoint into URL")?;
+
+        Ok(Self {
+            ledger: Arc::new(AnchorOutput::read_only(client)),
+            content_store: Arc::new(KuboStore::new(ipfs_endpoint)?),
+            concurrency_limit: Self::DEFAULT_CONCURRENCY_LIMIT,
+        })
+    }
+
+    /// Reads the alias content from the given `ledger` instead of the IOTA node.
+    pub fn with_ledger(mut self, ledger: impl LedgerAnchor + 'static) -> Self {
+        self.ledger = Arc::new(ledger);
+        self
+    }
+
+    /// Reads the index and chains of custody from the given `content_store` instead of the IPFS node.
+    pub fn with_content_store(mut self, content_store: impl ContentStore + 'static) -> Self {
+        self.content_store = Arc::new(content_store);
+        self
+    }
+
+    /// Sets the maximum number of chains of custody that are fetched concurrently.
+    pub fn with_concurrency_limit(mut self, concurrency_limit: usize) -> Self {
+        self.concurrency_limit = concurrency_limit.max(1);
+        self
+    }
+
+    /// Audits the DIDs in the current index of the alias, calling `on_progress` after every checked chain.
+    ///
+    /// Fails if the alias doesn't exist or its index can't be read, since there is nothing to audit then.
+    pub async fn audit(
+        &self,
+        alias_id: AliasId,
+        mut on_progress: impl FnMut(AuditProgress),
+    ) -> anyhow::Result<AuditReport> {
+        let state_index: u32 = self
+            .ledger
+            .state_index(alias_id)
+            .await?
+            .ok_or(ResolutionError::AliasNotFound(alias_id))?;
+        let content: AliasContent = self
+            .ledger
+            .read_content(alias_id)
+            .await?
+            .ok_or(ResolutionError::AliasNotFound(alias_id))?;
+
+        let index: DIDIndex = DIDIndex::from_json_slice(
+            &self
+                .content_store
+                .get(&content.index_cid)
+                .await
+                .with_context(|| format!("unable to fetch index {}", content.index_cid))?,
+        )?;
+
+        let total: usize = index.len();
+        on_progress(AuditProgress { checked: 0, total });
+
+        let mut checked: usize = 0;
+        let mut audits: Vec<ChainAudit> = futures::stream::iter(index.iter().map(|(did, cid)| {
+            let content: &AliasContent = &content;
+
+            async move { self.audit_chain(content, did, cid).await }
+        }))
+        .buffer_unordered(self.concurrency_limit)
+        .inspect(|_| {
+            checked += 1;
+            on_progress(AuditProgress { checked, total });
+        })
+        .collect()
+        .await;
+
+        // Report the findings in a stable order.
+        audits.sort_by_key(|audit| audit.did.to_string());
+
+        let mut findings: Vec<AuditFinding> = duplicate_cids(&index);
+        let mut leaves: Vec<(usize, IotaDID, Output<Blake2b256>)> = Vec::with_capacity(total);
+        let mut verified: usize = 0;
+        let mut complete: bool = true;
+
+        for audit in audits.iter() {
+            match audit.leaf {
+                Some((leaf_index, leaf)) => leaves.push((leaf_index, audit.did.clone(), leaf)),
+                None => complete = false,
+            }
+        }
+
+        let duplicates: Vec<AuditFinding> = duplicate_leaves(&leaves);
+
+        let root: RootCheck = if complete {
+            check_root(leaves, &content.merkle_root)
+        } else {
+            RootCheck::Incomplete
+        };
+
+        for mut audit in audits {
+            // A reproduced root commits to every leaf. Otherwise, only the stored proof can vouch for a chain,
+            // although it might have been generated against an earlier root.
+            if let Some((leaf_index, _)) = audit.leaf {
+                if root != RootCheck::Reproduced && !audit.proof_verified {
+                    audit.findings.push(AuditFinding::BadProof {
+                        did: audit.did,
+                        cid: audit.cid,
+                        leaf_index,
+                    });
+                }
+            }
+
+            if audit.findings.is_empty() {
+                verified += 1;
+            }
+
+            findings.extend(audit.findings);
+        }
+
+        findings.extend(duplicates);
+
+        Ok(AuditReport {
+            alias_id,
+            state_index,
+            index_cid: content.index_cid,
+            merkle_root: content.merkle_root,
+            dids: total,
+            verified,
+            root,
+            findings,
+        })
+    }
+
+    async fn audit_chain(&self, content: &AliasContent, did: &IotaDID, cid: &str) -> ChainAudit {
+        let mut audit = ChainAudit {
+            did: did.clone(),
+            cid: cid.to_owned(),
+            findings: Vec::new(),
+            leaf: None,
+            proof_verified: false,
+        };
+
+        let bytes: Bytes = match self.content_store.get(cid).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                audit.findings.push(AuditFinding::MissingCid {
+                    did: did.clone(),
+                    cid: cid.to_owned(),
+                    reason: format!("{err:#}"),
+                });
+                return audit;
+            }
+        };
+
+        let invalid_chain = |reason: String| AuditFinding::InvalidChain {
+            did: did.clone(),
+            cid: cid.to_owned(),
+            reason,
+        };
+
+        let mut unpacker = SliceUnpacker::new(bytes.as_ref());
+        let coc: VerifiableChainOfCustody =
+            match VerifiableChainOfCustody::unpack::<_, true>(&mut unpacker) {
+                Ok(coc) => coc,
+                Err(err) => {
+                    audit.findings.push(invalid_chain(format!("{err:?}")));
+                    return audit;
+                }
+            };
+
+        let serialized: Vec<u8> = match coc.chain_of_custody.serialize_to_vec() {
+            Ok(serialized) => serialized,
+            Err(err) => {
+                audit.findings.push(invalid_chain(format!("{err:#}")));
+                return audit;
+            }
+        };
+
+        let leaf_index: usize = coc.proof.index();
+        let leaf: Output<Blake2b256> = Blake2b256::new().hash_leaf(&serialized);
+
+        if let Some(first) = coc.chain_of_custody.0.first() {
+            if first.document.id() != did {
+                audit.findings.push(AuditFinding::MismatchedDid {
+                    did: did.clone(),
+                    cid: cid.to_owned(),
+                    chain_did: first.document.id().clone(),
+                });
+            }
+        }
+
+        if let Err(err) = coc.chain_of_custody.into_document() {
+            audit.findings.push(invalid_chain(format!("{err:#}")));
+        }
+
+        audit.proof_verified = coc.proof.verify_hash(&content.merkle_root, leaf);
+        audit.leaf = Some((leaf_index, leaf));
+
+        audit
+    }
+}
+
+/// The checks of a single chain of custody.
+struct ChainAudit {
+    did: IotaDID,
+    cid: String,
+    findings: Vec<AuditFinding>,
+    /// The position and hash of the chain's leaf, if the chain could be read.
+    leaf: Option<(usize, Output<Blake2b256>)>,
+    /// Whether the stored proof verifies against the merkle root.
+    proof_verified: bool,
+}
+
+fn duplicate_cids(index: &DIDIndex) -> Vec<AuditFinding> {
+    let mut dids_by_cid: HashMap<&String, Vec<IotaDID>> = HashMap::new();
+
+    for (did, cid) in index.iter() {
+        dids_by_cid.entry(cid).or_default().push(did.clone());
+    }
+
+    let mut duplicates: Vec<(&String, Vec<IotaDID>)> = dids_by_cid
+        .into_iter()
+        .filter(|(_, dids)| dids.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(cid, _)| *cid);
+
+    duplicates
+        .into_iter()
+        .map(|(cid, mut dids)| {
+            dids.sort_by_key(|did| did.to_string());
+
+            AuditFinding::DuplicateCid {
+                cid: cid.clone(),
+                dids,
+            }
+        })
+        .collect()
+}
+
+fn duplicate_leaves(leaves: &[(usize, IotaDID, Output<Blake2b256>)]) -> Vec<AuditFinding> {
+    let mut dids_by_leaf: HashMap<usize, Vec<IotaDID>> = HashMap::new();
+
+    for (leaf_index, did, _) in leaves {
+        dids_by_leaf
+            .entry(*leaf_index)
+            .or_default()
+            .push(did.clone());
+    }
+
+    let mut duplicates: Vec<(usize, Vec<IotaDID>)> = dids_by_leaf
+        .into_iter()
+        .filter(|(_, dids)| dids.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(leaf_index, _)| *leaf_index);
+
+    duplicates
+        .into_iter()
+        .map(|(leaf_index, dids)| AuditFinding::DuplicateLeaf { leaf_index, dids })
+        .collect()
+}
+
+/// Recomputes the merkle root from the `leaves` at their positions, which must cover the tree without gaps.
+pub(crate) fn check_root(
+    mut leaves: Vec<(usize, IotaDID, Output<Blake2b256>)>,
+    merkle_root: &[u8],
+) -> RootCheck {
+    leaves.sort_by_key(|(leaf_index, _, _)| *leaf_index);
+
+    let has_every_position: bool = leaves
+        .iter()
+        .enumerate()
+        .all(|(position, (leaf_index, _, _))| position == *leaf_index);
+
+    if !has_every_position {
+        return RootCheck::Mismatch;
+    }
+
+    let tree: MerkleTree<Blake2b256> = MerkleTree::from(
+        leaves
+            .into_iter()
+            .map(|(_, _, leaf)| leaf)
+            .collect::<Vec<_>>(),
+    );
+
+    if tree.root() == merkle_root {
+        RootCheck::Reproduced
+    } else {
+        RootCheck::Mismatch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto::hashes::{blake2b::Blake2b256, Digest, Output};
+    use identity_core::crypto::{KeyPair, KeyType};
+    use identity_iota_core::{did::IotaDID, document::IotaDocument};
+    use merkle_tree::{DigestExt, MerkleTree};
+
+    use super::{check_root, duplicate_leaves, AuditFinding, RootCheck};
+
+    fn did() -> IotaDID {
+        IotaDocument::new(&KeyPair::new(KeyType::Ed25519).unwrap())
+            .unwrap()
+            .id()
+            .clone()
+    }
+
+    fn leaf(data: &[u8]) -> Output<Blake2b256> {
+        Blake2b256::new().hash_leaf(data)
+    }
+
+    #[test]
+    fn test_leaves_reproduce_the_root() {
+        let mut tree: MerkleTree<Blake2b256> = MerkleTree::new();
+        for data in [b"a", b"b", b"c"] {
+            tree.push(data);
+        }
+        let root: Vec<u8> = tree.root();
+
+        let (a, b, c) = (did(), did(), did());
+
+        // The order in which the chains were fetched doesn't matter.
+        let leaves = vec![
+            (2, c.clone(), leaf(b"c")),
+            (0, a.clone(), leaf(b"a")),
+            (1, b.clone(), leaf(b"b")),
+        ];
+        assert_eq!(check_root(leaves, &root), RootCheck::Reproduced);
+
+        // A leaf that is not accounted for by the index.
+        let leaves = vec![(0, a.clone(), leaf(b"a")), (1, b.clone(), leaf(b"b"))];
+        assert_eq!(check_root(leaves, &root), RootCheck::Mismatch);
+
+        let leaves = vec![
+            (0, a, leaf(b"a")),
+            (1, b.clone(), leaf(b"b")),
+            (1, c.clone(), leaf(b"c")),
+        ];
+        assert_eq!(
+            duplicate_leaves(&leaves),
+            vec![AuditFinding::DuplicateLeaf {
+                leaf_index: 1,
+                dids: vec![b, c],
+            }]
+        );
+        assert_eq!(check_root(leaves, &root), RootCheck::Mismatch);
+    }
+}
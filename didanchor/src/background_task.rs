@@ -0,0 +1,67 @@
+use std::future::Future;
+
+use tokio::{
+    sync::{broadcast, oneshot},
+    task::JoinHandle,
+};
+
+/// A task running in the background that broadcasts events to its subscribers until it is shut down.
+///
+/// The tasks poll regularly, and an operation that fails is logged and retried with the next poll
+/// rather than stopping the task.
+pub(crate) struct BackgroundTask<T, R> {
+    task: JoinHandle<R>,
+    shutdown: oneshot::Sender<()>,
+    events: broadcast::Sender<T>,
+}
+
+impl<T, R> BackgroundTask<T, R>
+where
+    T: Clone + Send + 'static,
+    R: Send + 'static,
+{
+    /// Spawns the future returned by `run` on the current runtime. It is given the sender of the events
+    /// and a receiver that completes once the task is asked to shut down.
+    pub(crate) fn spawn<F>(
+        capacity: usize,
+        run: impl FnOnce(EventSender<T>, oneshot::Receiver<()>) -> F,
+    ) -> Self
+    where
+        F: Future<Output = R> + Send + 'static,
+    {
+        let (events, _) = broadcast::channel(capacity);
+        let (shutdown, shutdown_receiver) = oneshot::channel();
+
+        let task = tokio::spawn(run(EventSender(events.clone()), shutdown_receiver));
+
+        Self {
+            task,
+            shutdown,
+            events,
+        }
+    }
+
+    /// Returns a receiver that is sent every event from now on.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<T> {
+        self.events.subscribe()
+    }
+
+    /// Asks the task to shut down and returns its output.
+    pub(crate) async fn shutdown(self) -> anyhow::Result<R> {
+        // The task might have already stopped, in which case there is nothing to signal.
+        let _ = self.shutdown.send(());
+
+        Ok(self.task.await?)
+    }
+}
+
+/// Sends the events of a [`BackgroundTask`] to its subscribers.
+#[derive(Clone)]
+pub(crate) struct EventSender<T>(broadcast::Sender<T>);
+
+impl<T> EventSender<T> {
+    pub(crate) fn send(&self, event: T) {
+        // Sending only fails if there are no subscribers, which is fine.
+        let _ = self.0.send(event);
+    }
+}
@@ -1,27 +1,41 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use bytes::Bytes;
 use did_common::VerifiableChainOfCustody;
-use identity_core::convert::{FromJson, ToJson};
+use identity_core::convert::FromJson;
 use identity_iota_core::did::IotaDID;
-use ipfs_client::IpfsClient;
-use ipfs_cluster::IpfsCluster;
 use packable::{unpacker::SliceUnpacker, Packable, PackableExt};
 use url::Url;
 
+use crate::{ContentStore, IpfsClusterStore};
+
 /// Storage for Chains of custodies.
 #[derive(Clone)]
 pub struct ChainStorage {
-    ipfs_client: IpfsClient,
-    ipfs_cluster: IpfsCluster,
+    store: Arc<dyn ContentStore>,
 }
 
 impl ChainStorage {
+    /// The number of chains of custody that are added in a single batch.
+    pub const ADD_BATCH_SIZE: usize = 100;
+    /// The number of batches that are added concurrently.
+    pub const ADD_CONCURRENCY: usize = 4;
+    /// The number of chains of custody that are fetched concurrently.
+    pub const GET_CONCURRENCY: usize = 16;
+
+    /// Creates a storage backed by the IPFS cluster with the given addresses.
     pub fn new(ipfs_cluster_addrs: Vec<Url>, ipfs_node_addrs: Vec<Url>) -> anyhow::Result<Self> {
-        Ok(Self {
-            ipfs_client: IpfsClient::new(ipfs_node_addrs)?,
-            ipfs_cluster: IpfsCluster::new(ipfs_cluster_addrs)?,
-        })
+        Ok(Self::with_store(IpfsClusterStore::new(
+            ipfs_cluster_addrs,
+            ipfs_node_addrs,
+        )?))
+    }
+
+    /// Creates a storage backed by the given [`ContentStore`].
+    pub fn with_store(store: impl ContentStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
     }
 
     /// Adds and pins the given [`VerifiableChainOfCustody`].
@@ -36,17 +50,28 @@ impl ChainStorage {
 
         let packed: Vec<u8> = verif_chain_of_custody.pack_to_vec();
 
-        let cid = self.ipfs_cluster.add(packed).await?.cid;
+        self.store.add(packed).await
+    }
+
+    /// Adds and pins the given chains of custody and returns their CIDs in the same order.
+    pub async fn add_many(
+        &self,
+        verif_chains_of_custody: &[VerifiableChainOfCustody],
+    ) -> anyhow::Result<Vec<String>> {
+        log::debug!("ipfs add {} chains", verif_chains_of_custody.len());
+
+        let packed: Vec<Vec<u8>> = verif_chains_of_custody
+            .iter()
+            .map(PackableExt::pack_to_vec)
+            .collect();
 
-        Ok(cid)
+        self.store.add_many(packed).await
     }
 
     pub async fn unpin(&self, cid: &str) -> anyhow::Result<()> {
         log::debug!("ipfs pin rm {cid}");
 
-        self.ipfs_cluster.unpin(cid).await?;
-
-        Ok(())
+        self.store.unpin(cid).await
     }
 
     pub async fn get(
@@ -64,7 +89,8 @@ impl ChainStorage {
 
         let mut unpacker = SliceUnpacker::new(bytes.as_ref());
         let coc: VerifiableChainOfCustody =
-            VerifiableChainOfCustody::unpack::<_, false>(&mut unpacker).expect("TODO");
+            VerifiableChainOfCustody::unpack::<_, false>(&mut unpacker)
+                .map_err(|err| anyhow::anyhow!("invalid chain of custody at {cid}: {err:?}"))?;
 
         Ok(Some(coc))
     }
@@ -80,15 +106,11 @@ impl ChainStorage {
     /// Publishes the given [`DIDIndex`] and returns its CID.
     pub async fn publish_index(&self, index: &DIDIndex) -> anyhow::Result<String> {
         log::debug!("publishing index");
-        let json: Vec<u8> = index.to_json_vec()?;
-
-        let cid = self.ipfs_cluster.add(json).await?.cid;
-
-        Ok(cid)
+        self.store.publish_index(index).await
     }
 
     async fn get_bytes(&self, cid: &str) -> anyhow::Result<Bytes> {
-        self.ipfs_client.cat(cid).await
+        self.store.get(cid).await
     }
 }
 
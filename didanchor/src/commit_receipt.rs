@@ -0,0 +1,88 @@
+use std::time::{Duration, SystemTime};
+
+use identity_iota_core::did::IotaDID;
+use iota_client::block::{
+    output::{AliasId, OutputId},
+    payload::transaction::TransactionId,
+    BlockId,
+};
+
+/// Where a [`LedgerAnchor`](crate::LedgerAnchor) anchored alias content.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LedgerReceipt {
+    pub alias_id: AliasId,
+    /// The index of the alias state that holds the content.
+    pub state_index: u32,
+    /// The following are only known to ledgers made of blocks and outputs, and only if publishing
+    /// succeeded. They are unknown if publishing reported an error although the content was anchored.
+    pub block_id: Option<BlockId>,
+    pub transaction_id: Option<TransactionId>,
+    pub output_id: Option<OutputId>,
+    /// The amount of base tokens deposited in the Alias Output.
+    pub storage_deposit: Option<u64>,
+}
+
+impl LedgerReceipt {
+    /// Creates a receipt that only holds the alias state.
+    pub fn new(alias_id: AliasId, state_index: u32) -> Self {
+        Self {
+            alias_id,
+            state_index,
+            block_id: None,
+            transaction_id: None,
+            output_id: None,
+            storage_deposit: None,
+        }
+    }
+}
+
+/// The chain of custody of a DID as anchored by a commit.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AnchoredChain {
+    pub did: IotaDID,
+    pub cid: String,
+    /// The packed merkle proof of the chain against the commit's merkle root,
+    /// which is also stored together with the chain.
+    pub proof: Vec<u8>,
+}
+
+/// When a commit ran and how long its phases took.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CommitTiming {
+    pub started_at: SystemTime,
+    pub anchored_at: SystemTime,
+    /// The time it took to pin the chains of custody and the index.
+    pub prepare: Duration,
+    /// The time it took to anchor the index in the ledger.
+    pub publish: Duration,
+}
+
+/// The receipt of a commit, returned by [`Anchor::commit_changes`](crate::Anchor::commit_changes).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CommitReceipt {
+    pub ledger: LedgerReceipt,
+    /// The index that was anchored before the commit, if any.
+    pub previous_index_cid: Option<String>,
+    pub index_cid: String,
+    pub merkle_root: Vec<u8>,
+    /// The chains of custody of the DIDs whose changes were committed.
+    pub chains: Vec<AnchoredChain>,
+    pub timing: CommitTiming,
+}
+
+impl CommitReceipt {
+    /// Returns the id of the alias the changes were anchored in.
+    pub fn alias_id(&self) -> AliasId {
+        self.ledger.alias_id
+    }
+
+    /// Returns the DIDs whose changes were committed.
+    pub fn dids(&self) -> Vec<IotaDID> {
+        self.chains.iter().map(|chain| chain.did.clone()).collect()
+    }
+
+    /// Returns the anchored chain of custody of `did`, if the commit contained changes of it.
+    pub fn chain(&self, did: &IotaDID) -> Option<&AnchoredChain> {
+        self.chains.iter().find(|chain| &chain.did == did)
+    }
+}
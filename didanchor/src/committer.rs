@@ -0,0 +1,250 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use tokio::sync::{broadcast, oneshot, Mutex};
+
+use crate::{
+    background_task::{BackgroundTask, EventSender},
+    Anchor, CommitReceipt,
+};
+
+/// Determines when the [`Committer`] commits the pending changes of an [`Anchor`].
+///
+/// Changes are committed as soon as any of the configured conditions is met.
+/// Nothing is committed while no changes are pending.
+#[derive(Debug, Clone)]
+pub struct CommitPolicy {
+    /// Commit if this much time has passed since the last commit.
+    pub interval: Option<Duration>,
+    /// Commit if at least this many DIDs have pending changes.
+    pub max_pending: Option<usize>,
+    /// Commit if the oldest pending change was accepted at least this long ago.
+    pub max_age: Option<Duration>,
+    /// How often the conditions are checked.
+    pub check_interval: Duration,
+    /// How long to wait before retrying after a failed commit.
+    /// The wait doubles with every further failure, up to `max_retry_backoff`.
+    pub retry_backoff: Duration,
+    /// The longest wait before retrying after failed commits.
+    pub max_retry_backoff: Duration,
+}
+
+impl CommitPolicy {
+    /// Returns whether changes should be committed given the number of `pending` changes,
+    /// the age of the oldest one and the time that passed since the last commit.
+    pub fn is_due(
+        &self,
+        pending: usize,
+        oldest_pending_age: Option<Duration>,
+        since_last_commit: Duration,
+    ) -> bool {
+        if pending == 0 {
+            return false;
+        }
+
+        let interval_elapsed = self
+            .interval
+            .map(|interval| since_last_commit >= interval)
+            .unwrap_or(false);
+        let max_pending_reached = self
+            .max_pending
+            .map(|max_pending| pending >= max_pending)
+            .unwrap_or(false);
+        let max_age_reached = match (self.max_age, oldest_pending_age) {
+            (Some(max_age), Some(age)) => age >= max_age,
+            _ => false,
+        };
+
+        interval_elapsed || max_pending_reached || max_age_reached
+    }
+
+    /// Returns how long to wait before retrying after the given number of consecutive `failures`.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let factor: u32 = 1u32
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+
+        self.retry_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_retry_backoff)
+            .min(self.max_retry_backoff)
+    }
+}
+
+impl Default for CommitPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Some(Duration::from_secs(60)),
+            max_pending: None,
+            max_age: None,
+            check_interval: Duration::from_secs(1),
+            retry_backoff: Duration::from_secs(5),
+            max_retry_backoff: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Commits the changes of an [`Anchor`] in the background according to a [`CommitPolicy`].
+pub struct Committer {
+    task: BackgroundTask<CommitReceipt, anyhow::Result<()>>,
+}
+
+impl Committer {
+    const EVENT_CAPACITY: usize = 64;
+
+    /// Spawns a task on the current runtime that commits the changes of `anchor` according to `policy`.
+    pub fn spawn(anchor: Arc<Mutex<Anchor>>, policy: CommitPolicy) -> Self {
+        let task = BackgroundTask::spawn(Self::EVENT_CAPACITY, |events, shutdown| {
+            Self::run(anchor, policy, events, shutdown)
+        });
+
+        Self { task }
+    }
+
+    /// Returns a receiver that is sent the receipt of every commit that anchored pending changes.
+    pub fn subscribe(&self) -> broadcast::Receiver<CommitReceipt> {
+        self.task.subscribe()
+    }
+
+    /// Stops the committer after committing all remaining pending changes.
+    ///
+    /// Returns the error of that final commit, in which case the changes are left pending.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.task.shutdown().await?
+    }
+
+    async fn run(
+        anchor: Arc<Mutex<Anchor>>,
+        policy: CommitPolicy,
+        events: EventSender<CommitReceipt>,
+        mut shutdown: oneshot::Receiver<()>,
+    ) -> anyhow::Result<()> {
+        let mut ticker = tokio::time::interval(policy.check_interval);
+        let mut last_commit = Instant::now();
+        let mut failures: u32 = 0;
+        let mut retry_at: Option<Instant> = None;
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    // A commit that keeps failing would otherwise be due again right away.
+                    if retry_at.map(|retry_at| Instant::now() < retry_at).unwrap_or(false) {
+                        continue;
+                    }
+
+                    let mut anchor = anchor.lock().await;
+
+                    let oldest_pending_age: Option<Duration> = anchor
+                        .oldest_pending_change()
+                        .and_then(|time| time.elapsed().ok());
+
+                    if policy.is_due(
+                        anchor.pending_count(),
+                        oldest_pending_age,
+                        last_commit.elapsed(),
+                    ) {
+                        match Self::commit(&mut anchor, &events).await {
+                            Ok(()) => {
+                                failures = 0;
+                                retry_at = None;
+                            }
+                            Err(err) => {
+                                failures = failures.saturating_add(1);
+                                let backoff: Duration = policy.backoff(failures);
+                                retry_at = Some(Instant::now() + backoff);
+
+                                log::error!("{err:#}, retrying in {}s", backoff.as_secs());
+                            }
+                        }
+                        last_commit = Instant::now();
+                    }
+                }
+                _ = &mut shutdown => {
+                    let mut anchor = anchor.lock().await;
+
+                    if anchor.pending_count() > 0 {
+                        log::debug!("committing remaining changes before shutting down");
+                        return Self::commit(&mut anchor, &events).await;
+                    }
+
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    async fn commit(
+        anchor: &mut Anchor,
+        events: &EventSender<CommitReceipt>,
+    ) -> anyhow::Result<()> {
+        let pending: usize = anchor.pending_count();
+
+        let receipt: CommitReceipt = anchor
+            .commit_changes()
+            .await
+            .with_context(|| format!("failed to commit {pending} change(s)"))?;
+
+        events.send(receipt);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::CommitPolicy;
+
+    #[test]
+    fn test_commit_policy_is_due() {
+        let policy = CommitPolicy {
+            interval: Some(Duration::from_secs(60)),
+            max_pending: Some(100),
+            max_age: Some(Duration::from_secs(10)),
+            ..CommitPolicy::default()
+        };
+
+        // Nothing is committed without pending changes.
+        assert!(!policy.is_due(0, None, Duration::from_secs(120)));
+
+        assert!(!policy.is_due(1, Some(Duration::from_secs(1)), Duration::from_secs(1)));
+        assert!(policy.is_due(1, Some(Duration::from_secs(1)), Duration::from_secs(60)));
+        assert!(policy.is_due(100, Some(Duration::from_secs(1)), Duration::from_secs(1)));
+        assert!(policy.is_due(1, Some(Duration::from_secs(10)), Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_commit_policy_without_conditions() {
+        let policy = CommitPolicy {
+            interval: None,
+            max_pending: None,
+            max_age: None,
+            ..CommitPolicy::default()
+        };
+
+        assert!(!policy.is_due(
+            1000,
+            Some(Duration::from_secs(1000)),
+            Duration::from_secs(1000)
+        ));
+    }
+
+    #[test]
+    fn test_commit_policy_backoff() {
+        let policy = CommitPolicy {
+            retry_backoff: Duration::from_secs(5),
+            max_retry_backoff: Duration::from_secs(60),
+            ..CommitPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(5));
+        assert_eq!(policy.backoff(2), Duration::from_secs(10));
+        assert_eq!(policy.backoff(4), Duration::from_secs(40));
+        assert_eq!(policy.backoff(5), Duration::from_secs(60));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(60));
+    }
+}
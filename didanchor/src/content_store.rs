@@ -0,0 +1,336 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use cid::{multihash::MultihashGeneric, Cid};
+use crypto::hashes::{blake2b::Blake2b256, Digest};
+use identity_core::convert::ToJson;
+use ipfs_client::IpfsClient;
+use ipfs_cluster::{AddManyError, IpfsCluster};
+use url::Url;
+
+use crate::DIDIndex;
+
+/// Content-addressed storage for chains of custody and indices.
+#[async_trait::async_trait]
+pub trait ContentStore: Send + Sync {
+    /// Adds and pins `data` and returns its CID.
+    async fn add(&self, data: Vec<u8>) -> anyhow::Result<String>;
+
+    /// Adds and pins all of `data` and returns their CIDs in the same order.
+    ///
+    /// If this fails after some of the content was pinned, the error is a [`PartialAdd`] with their CIDs.
+    async fn add_many(&self, data: Vec<Vec<u8>>) -> anyhow::Result<Vec<String>> {
+        let mut cids: Vec<String> = Vec::with_capacity(data.len());
+
+        for data in data {
+            match self.add(data).await {
+                Ok(cid) => cids.push(cid),
+                Err(error) if cids.is_empty() => return Err(error),
+                Err(error) => return Err(PartialAdd { added: cids, error }.into()),
+            }
+        }
+
+        Ok(cids)
+    }
+
+    /// Returns the content with the given `cid`.
+    async fn get(&self, cid: &str) -> anyhow::Result<Bytes>;
+
+    /// Unpins the content with the given `cid`.
+    async fn unpin(&self, cid: &str) -> anyhow::Result<()>;
+
+    /// Publishes the given [`DIDIndex`] and returns its CID.
+    async fn publish_index(&self, index: &DIDIndex) -> anyhow::Result<String> {
+        self.add(index.to_json_vec()?).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ContentStore + ?Sized> ContentStore for Arc<T> {
+    async fn add(&self, data: Vec<u8>) -> anyhow::Result<String> {
+        self.as_ref().add(data).await
+    }
+
+    async fn add_many(&self, data: Vec<Vec<u8>>) -> anyhow::Result<Vec<String>> {
+        self.as_ref().add_many(data).await
+    }
+
+    async fn get(&self, cid: &str) -> anyhow::Result<Bytes> {
+        self.as_ref().get(cid).await
+    }
+
+    async fn unpin(&self, cid: &str) -> anyhow::Result<()> {
+        self.as_ref().unpin(cid).await
+    }
+
+    async fn publish_index(&self, index: &DIDIndex) -> anyhow::Result<String> {
+        self.as_ref().publish_index(index).await
+    }
+}
+
+/// Adding content with [`ContentStore::add_many`] failed after some of it was pinned.
+///
+/// Returned wrapped in an [`anyhow::Error`] and can be obtained through [`anyhow::Error::downcast_ref`],
+/// so that the `added` content can be unpinned again.
+#[derive(Debug)]
+pub struct PartialAdd {
+    pub added: Vec<String>,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for PartialAdd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "adding failed after {} content(s) were pinned: {:#}",
+            self.added.len(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for PartialAdd {}
+
+/// Stores content in an IPFS cluster, which pins it to every peer.
+///
+/// Content is read via the RPC API of the cluster's IPFS nodes.
+#[derive(Clone)]
+pub struct IpfsClusterStore {
+    ipfs_client: IpfsClient,
+    ipfs_cluster: IpfsCluster,
+}
+
+impl IpfsClusterStore {
+    pub fn new(ipfs_cluster_addrs: Vec<Url>, ipfs_node_addrs: Vec<Url>) -> anyhow::Result<Self> {
+        Ok(Self {
+            ipfs_client: IpfsClient::new(ipfs_node_addrs)?,
+            ipfs_cluster: IpfsCluster::new(ipfs_cluster_addrs)?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentStore for IpfsClusterStore {
+    async fn add(&self, data: Vec<u8>) -> anyhow::Result<String> {
+        Ok(self.ipfs_cluster.add(data).await?.cid)
+    }
+
+    async fn add_many(&self, data: Vec<Vec<u8>>) -> anyhow::Result<Vec<String>> {
+        // The names only serve to map the responses back to the content.
+        let files: Vec<(String, Vec<u8>)> = data
+            .into_iter()
+            .enumerate()
+            .map(|(i, data)| (i.to_string(), data))
+            .collect();
+
+        match self.ipfs_cluster.add_many(files).await {
+            Ok(responses) => Ok(responses.into_iter().map(|response| response.cid).collect()),
+            Err(error) => match error.downcast::<AddManyError>() {
+                Ok(AddManyError { added, error }) => Err(PartialAdd {
+                    added: added.into_iter().map(|response| response.cid).collect(),
+                    error,
+                }
+                .into()),
+                Err(error) => Err(error),
+            },
+        }
+    }
+
+    async fn get(&self, cid: &str) -> anyhow::Result<Bytes> {
+        self.ipfs_client.cat(cid).await
+    }
+
+    async fn unpin(&self, cid: &str) -> anyhow::Result<()> {
+        self.ipfs_cluster.unpin(cid).await
+    }
+}
+
+/// Stores content in a single IPFS (Kubo) node.
+#[derive(Clone)]
+pub struct KuboStore {
+    ipfs_client: IpfsClient,
+}
+
+impl KuboStore {
+    pub fn new(ipfs_node_addr: Url) -> anyhow::Result<Self> {
+        Ok(Self {
+            ipfs_client: IpfsClient::new(vec![ipfs_node_addr])?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentStore for KuboStore {
+    async fn add(&self, data: Vec<u8>) -> anyhow::Result<String> {
+        self.ipfs_client.add(data).await
+    }
+
+    async fn get(&self, cid: &str) -> anyhow::Result<Bytes> {
+        self.ipfs_client.cat(cid).await
+    }
+
+    async fn unpin(&self, cid: &str) -> anyhow::Result<()> {
+        self.ipfs_client.pin_rm(cid).await
+    }
+}
+
+/// Stores content as files in a local directory, named by their CID.
+///
+/// The CIDs are computed with [`content_id`], so they differ from the ones IPFS assigns to the same content.
+#[derive(Debug, Clone)]
+pub struct FilesystemStore {
+    path: PathBuf,
+}
+
+impl FilesystemStore {
+    /// Creates a store in the directory at `path`, which is created if it doesn't exist.
+    pub async fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        tokio::fs::create_dir_all(path.as_ref()).await?;
+
+        Ok(Self {
+            path: path.as_ref().to_owned(),
+        })
+    }
+
+    fn file_path(&self, cid: &str) -> anyhow::Result<PathBuf> {
+        // Parsing ensures the cid can't be used to escape the directory.
+        let cid: Cid = Cid::try_from(cid)?;
+
+        Ok(self.path.join(cid.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentStore for FilesystemStore {
+    async fn add(&self, data: Vec<u8>) -> anyhow::Result<String> {
+        let cid: String = content_id(&data)?;
+
+        let path: PathBuf = self.file_path(&cid)?;
+        let tmp_path: PathBuf = path.with_extension("tmp");
+
+        tokio::fs::write(&tmp_path, data).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+
+        Ok(cid)
+    }
+
+    async fn get(&self, cid: &str) -> anyhow::Result<Bytes> {
+        Ok(Bytes::from(tokio::fs::read(self.file_path(cid)?).await?))
+    }
+
+    async fn unpin(&self, cid: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.file_path(cid)?).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(anyhow::anyhow!(err)),
+        }
+    }
+}
+
+/// Stores content in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    content: Mutex<HashMap<String, Bytes>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether content with the given `cid` is stored.
+    pub fn contains(&self, cid: &str) -> bool {
+        self.content
+            .lock()
+            .expect("the lock should not be poisoned")
+            .contains_key(cid)
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentStore for MemoryStore {
+    async fn add(&self, data: Vec<u8>) -> anyhow::Result<String> {
+        let cid: String = content_id(&data)?;
+
+        self.content
+            .lock()
+            .expect("the lock should not be poisoned")
+            .insert(cid.clone(), Bytes::from(data));
+
+        Ok(cid)
+    }
+
+    async fn get(&self, cid: &str) -> anyhow::Result<Bytes> {
+        self.content
+            .lock()
+            .expect("the lock should not be poisoned")
+            .get(cid)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no content with cid {cid}"))
+    }
+
+    async fn unpin(&self, cid: &str) -> anyhow::Result<()> {
+        self.content
+            .lock()
+            .expect("the lock should not be poisoned")
+            .remove(cid);
+
+        Ok(())
+    }
+}
+
+/// Computes the CIDv1 of `data` as raw content hashed with blake2b-256.
+pub fn content_id(data: &[u8]) -> anyhow::Result<String> {
+    const RAW_CODEC: u64 = 0x55;
+    const BLAKE2B_256_CODE: u64 = 0xb220;
+
+    let digest = Blake2b256::digest(data);
+    let multihash = MultihashGeneric::<64>::wrap(BLAKE2B_256_CODE, digest.as_ref())?;
+
+    Ok(Cid::new_v1(RAW_CODEC, multihash).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_id, ContentStore, FilesystemStore, MemoryStore};
+
+    async fn test_store(store: &dyn ContentStore) {
+        let data: Vec<u8> = b"chain of custody".to_vec();
+
+        let cid: String = store.add(data.clone()).await.unwrap();
+        assert_eq!(cid, content_id(&data).unwrap());
+        assert_eq!(store.get(&cid).await.unwrap().as_ref(), data.as_slice());
+
+        let cids: Vec<String> = store
+            .add_many(vec![b"a".to_vec(), b"b".to_vec()])
+            .await
+            .unwrap();
+        assert_eq!(store.get(&cids[1]).await.unwrap().as_ref(), b"b");
+
+        store.unpin(&cid).await.unwrap();
+        assert!(store.get(&cid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        test_store(&MemoryStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_store() {
+        let path = std::env::temp_dir().join(format!("content-store-{}", rand::random::<u64>()));
+
+        test_store(&FilesystemStore::new(&path).await.unwrap()).await;
+
+        assert!(FilesystemStore::new(&path)
+            .await
+            .unwrap()
+            .get("../anchor_config.toml")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use did_common::VerifiableChainOfCustody;
+use identity_did::{did::CoreDID, document::CoreDocument};
+use identity_iota_core::did::IotaDID;
+use iota_client::block::{
+    output::{dto::OutputDto, AliasId, AliasOutput, Output, OutputId},
+    payload::milestone::MilestonePayload,
+};
+use packable::{unpacker::SliceUnpacker, Packable};
+
+use crate::{parse_did, AliasContent, LedgerInclusionProof, MilestoneCommittee};
+
+/// A self-contained proof that a DID document was published by the controller of an Alias Output.
+///
+/// It contains everything necessary to convince a third party of the existence of a certain
+/// state of a DID document and can be verified offline with [`verify_evidence`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EvidenceBundle {
+    /// The DID of the form `did:iota:<alias_id>:<tag>` the evidence is about.
+    pub did: CoreDID,
+    /// The id of the Alias Output that anchors the chain of custody.
+    pub output_id: String,
+    /// The Alias Output that anchors the chain of custody.
+    pub alias_output: OutputDto,
+    /// The proof that the block containing the Alias Output was included in the ledger.
+    pub inclusion_proof: LedgerInclusionProof,
+    /// The content of the Alias Output.
+    pub alias_content: AliasContent,
+    /// The packed [`VerifiableChainOfCustody`] of the DID.
+    #[serde(with = "prefix_hex_bytes")]
+    pub chain_of_custody: Vec<u8>,
+}
+
+/// The result of successfully verifying an [`EvidenceBundle`].
+#[derive(Debug, Clone)]
+pub struct VerifiedEvidence {
+    /// The DID document as of the anchored chain of custody.
+    pub document: CoreDocument,
+    /// The index of the milestone that included the Alias Output.
+    pub milestone_index: u32,
+    /// The unix timestamp of the milestone that included the Alias Output.
+    pub milestone_timestamp: u32,
+}
+
+/// Verifies the given `evidence` without access to the network.
+///
+/// Checks that
+/// - the Alias Output is contained in the included block and belongs to the alias of the DID,
+/// - the block was referenced by the milestone in the inclusion proof,
+/// - the milestone is signed by the `committee` of the network,
+/// - the content matches the state metadata of the Alias Output,
+/// - the chain of custody belongs to the DID, is valid and is committed to by the merkle root.
+///
+/// Without the milestone signatures, anyone could make up a bundle that passes the other checks,
+/// which is why the `committee` is required.
+pub fn verify_evidence(
+    evidence: &EvidenceBundle,
+    committee: &MilestoneCommittee,
+) -> anyhow::Result<VerifiedEvidence> {
+    check_evidence(evidence, Some(committee))
+}
+
+/// Checks the given `evidence` like [`verify_evidence`], but only verifies the milestone signatures
+/// if a `committee` is given.
+///
+/// This is only meaningful for evidence from a trusted source, e.g. to check a freshly exported bundle.
+pub(crate) fn check_evidence(
+    evidence: &EvidenceBundle,
+    committee: Option<&MilestoneCommittee>,
+) -> anyhow::Result<VerifiedEvidence> {
+    let (alias_id, did): (AliasId, IotaDID) = parse_did(&evidence.did)?;
+
+    let output_id: OutputId = evidence.output_id.parse()?;
+
+    let alias_output: AliasOutput = evidence
+        .inclusion_proof
+        .alias_output(&output_id, &alias_id)?;
+
+    if Output::try_from(&evidence.alias_output)? != Output::Alias(alias_output.clone()) {
+        anyhow::bail!("the alias output does not match output {output_id} of the transaction");
+    }
+
+    let (content, _) = AliasContent::from_state_metadata(alias_output.state_metadata())?;
+
+    if content != evidence.alias_content {
+        anyhow::bail!("the alias content does not match the state metadata of the alias output");
+    }
+
+    match committee {
+        Some(committee) => evidence.inclusion_proof.verify_with_committee(committee)?,
+        None => evidence.inclusion_proof.verify()?,
+    };
+
+    let milestone: MilestonePayload = evidence.inclusion_proof.milestone()?;
+
+    let mut unpacker = SliceUnpacker::new(evidence.chain_of_custody.as_slice());
+    let coc: VerifiableChainOfCustody = VerifiableChainOfCustody::unpack::<_, true>(&mut unpacker)
+        .map_err(|err| anyhow::anyhow!("invalid chain of custody: {err:?}"))?;
+
+    match coc.chain_of_custody.0.first() {
+        Some(first) if first.document.id() == &did => (),
+        _ => anyhow::bail!("the chain of custody does not belong to {did}"),
+    }
+
+    let serialized = coc.chain_of_custody.serialize_to_vec()?;
+
+    if !coc.proof.verify(&content.merkle_root, serialized) {
+        anyhow::bail!("invalid merkle proof for {did}");
+    }
+
+    let document: CoreDocument = coc.chain_of_custody.into_document()?;
+
+    Ok(VerifiedEvidence {
+        document,
+        milestone_index: *milestone.essence().index(),
+        milestone_timestamp: milestone.essence().timestamp(),
+    })
+}
+
+pub(crate) mod prefix_hex_bytes {
+    //! Provides serialization for bytes as a prefix-hex encoded string.
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&prefix_hex::encode(bytes.to_vec()))
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        prefix_hex::decode(&string).map_err(|err| de::Error::custom(format!("{err:?}")))
+    }
+}
@@ -0,0 +1,23 @@
+use crypto::hashes::{blake2b::Blake2b256, Digest};
+use iota_client::block::address::{Address, Ed25519Address};
+
+/// Signs the transactions of an anchor with an Ed25519 key that is kept outside of it, e.g. in an HSM or KMS.
+///
+/// The Alias Output and the outputs that fund it must be controlled by the address of the signer's key.
+#[async_trait::async_trait]
+pub trait ExternalSigner: Send + Sync {
+    /// Returns the public key of the signer.
+    async fn public_key(&self) -> anyhow::Result<[u8; 32]>;
+
+    /// Signs the hash of a transaction essence.
+    async fn sign(&self, essence_hash: &[u8; 32]) -> anyhow::Result<[u8; 64]>;
+
+    /// Returns the address controlled by the signer.
+    async fn address(&self) -> anyhow::Result<Address> {
+        let public_key: [u8; 32] = self.public_key().await?;
+
+        Ok(Address::Ed25519(Ed25519Address::new(
+            Blake2b256::digest(public_key).into(),
+        )))
+    }
+}
@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use tokio::fs::File;
+
+/// Makes a rename into the directory of `path` durable.
+#[cfg(unix)]
+pub(crate) async fn sync_parent_dir(path: &Path) -> anyhow::Result<()> {
+    let dir: &Path = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    File::open(dir).await?.sync_all().await?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) async fn sync_parent_dir(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
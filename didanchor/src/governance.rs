@@ -0,0 +1,218 @@
+use iota_client::{
+    api::PreparedTransactionData,
+    block::{
+        address::Address,
+        input::UtxoInput,
+        output::{
+            unlock_condition::{
+                GovernorAddressUnlockCondition, StateControllerAddressUnlockCondition,
+            },
+            AliasId, AliasOutput, AliasOutputBuilder, UnlockCondition,
+        },
+        payload::{transaction::TransactionPayload, Payload},
+        Block, BlockId,
+    },
+    secret::SecretManager,
+    Client,
+};
+
+use crate::{resolve_alias_output, AnchorOutput};
+
+/// A governance transition of an Alias Output, which must be signed by its governor.
+///
+/// Unlike state transitions, governance transitions keep the state index and metadata,
+/// so anchored content is unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GovernanceChange {
+    /// Hands the right to publish new states to the given address, e.g. to replace a compromised hot key.
+    RotateStateController(Address),
+    /// Hands the right to govern the alias to the given address.
+    TransferGovernance(Address),
+}
+
+impl GovernanceChange {
+    /// Returns the Alias Output that results from applying the change to the `current` output of `alias_id`.
+    pub fn apply(&self, alias_id: AliasId, current: &AliasOutput) -> anyhow::Result<AliasOutput> {
+        let (state_controller, governor): (Address, Address) = match *self {
+            GovernanceChange::RotateStateController(address) => {
+                (address, *current.governor_address())
+            }
+            GovernanceChange::TransferGovernance(address) => {
+                (*current.state_controller_address(), address)
+            }
+        };
+
+        let mut builder: AliasOutputBuilder = AliasOutputBuilder::from(current)
+            .with_unlock_conditions(vec![
+                UnlockCondition::StateControllerAddress(
+                    StateControllerAddressUnlockCondition::new(state_controller),
+                ),
+                UnlockCondition::GovernorAddress(GovernorAddressUnlockCondition::new(governor)),
+            ]);
+
+        if current.alias_id().is_null() {
+            builder = builder.with_alias_id(alias_id);
+        }
+
+        Ok(builder.finish()?)
+    }
+}
+
+impl AnchorOutput {
+    /// Builds the unsigned transaction that applies `change` to the current Alias Output.
+    ///
+    /// The transaction can be signed offline by the governor, e.g. with iota.rs' offline signing,
+    /// and then be submitted with [`AnchorOutput::submit_governance_transaction`].
+    pub async fn prepare_governance_transaction(
+        &self,
+        change: GovernanceChange,
+    ) -> anyhow::Result<PreparedTransactionData> {
+        Self::prepare_governance(&self.client, self.alias_id, change).await
+    }
+
+    /// Hands the right to publish new states to `address`, signing as the governor with `governor`.
+    ///
+    /// Unless `address` belongs to this output's signer, it can no longer publish content afterwards.
+    pub async fn rotate_state_controller(
+        &self,
+        address: Address,
+        governor: &SecretManager,
+    ) -> anyhow::Result<BlockId> {
+        self.apply_governance_change(GovernanceChange::RotateStateController(address), governor)
+            .await
+    }
+
+    /// Hands the right to govern the alias to `address`, signing as the current governor with `governor`.
+    pub async fn transfer_governance(
+        &self,
+        address: Address,
+        governor: &SecretManager,
+    ) -> anyhow::Result<BlockId> {
+        self.apply_governance_change(GovernanceChange::TransferGovernance(address), governor)
+            .await
+    }
+
+    /// Submits a governance transaction that was signed offline and waits for it to be included.
+    pub async fn submit_governance_transaction(
+        &self,
+        payload: TransactionPayload,
+    ) -> anyhow::Result<BlockId> {
+        let block: Block = self
+            .client
+            .block()
+            .finish_block(Some(Payload::Transaction(Box::new(payload))))
+            .await?;
+
+        let _ = self
+            .client
+            .retry_until_included(&block.id(), None, None)
+            .await?;
+
+        log::debug!(
+            "governance transaction of alias {} included in block {}",
+            self.alias_id,
+            block.id()
+        );
+
+        Ok(block.id())
+    }
+
+    async fn apply_governance_change(
+        &self,
+        change: GovernanceChange,
+        governor: &SecretManager,
+    ) -> anyhow::Result<BlockId> {
+        let prepared: PreparedTransactionData =
+            Self::prepare_governance(&self.client, self.alias_id, change).await?;
+
+        let payload: TransactionPayload = self
+            .client
+            .block()
+            .with_secret_manager(governor)
+            .sign_transaction(prepared)
+            .await?;
+
+        self.submit_governance_transaction(payload).await
+    }
+
+    async fn prepare_governance(
+        client: &Client,
+        alias_id: AliasId,
+        change: GovernanceChange,
+    ) -> anyhow::Result<PreparedTransactionData> {
+        if alias_id.is_null() {
+            anyhow::bail!("the alias has not been created yet");
+        }
+
+        let (alias_id, output_id, current) = resolve_alias_output(client, alias_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no output found for alias id {alias_id}"))?;
+
+        let output: AliasOutput = change.apply(alias_id, &current)?;
+
+        // The amount is unchanged, so the Alias Output is the only input.
+        Ok(client
+            .block()
+            .with_inputs(vec![UtxoInput::from(output_id)])?
+            .with_outputs(vec![output.into()])?
+            .prepare_transaction()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iota_client::block::{
+        address::{Address, Ed25519Address},
+        output::{
+            unlock_condition::{
+                GovernorAddressUnlockCondition, StateControllerAddressUnlockCondition,
+            },
+            AliasId, AliasOutput, AliasOutputBuilder, UnlockCondition,
+        },
+    };
+
+    use super::GovernanceChange;
+
+    fn address(byte: u8) -> Address {
+        Address::Ed25519(Ed25519Address::new([byte; 32]))
+    }
+
+    fn alias_output(alias_id: AliasId) -> AliasOutput {
+        AliasOutputBuilder::new_with_amount(1_000_000, alias_id)
+            .unwrap()
+            .with_state_index(3)
+            .with_foundry_counter(0)
+            .with_state_metadata(b"content".to_vec())
+            .add_unlock_condition(UnlockCondition::StateControllerAddress(
+                StateControllerAddressUnlockCondition::new(address(1)),
+            ))
+            .add_unlock_condition(UnlockCondition::GovernorAddress(
+                GovernorAddressUnlockCondition::new(address(2)),
+            ))
+            .finish()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_governance_change_keeps_state() {
+        let alias_id = AliasId::new([7; 32]);
+        let current = alias_output(alias_id);
+
+        let rotated = GovernanceChange::RotateStateController(address(3))
+            .apply(alias_id, &current)
+            .unwrap();
+        assert_eq!(rotated.state_controller_address(), &address(3));
+        assert_eq!(rotated.governor_address(), &address(2));
+
+        let transferred = GovernanceChange::TransferGovernance(address(4))
+            .apply(alias_id, &rotated)
+            .unwrap();
+        assert_eq!(transferred.state_controller_address(), &address(3));
+        assert_eq!(transferred.governor_address(), &address(4));
+
+        assert_eq!(transferred.state_index(), current.state_index());
+        assert_eq!(transferred.state_metadata(), current.state_metadata());
+        assert_eq!(transferred.alias_id(), &alias_id);
+    }
+}
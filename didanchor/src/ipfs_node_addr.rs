@@ -1,7 +1,7 @@
 use multiaddr::{Multiaddr, Protocol};
 use url::Url;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct IpfsNodePublicAddress {
     #[serde(with = "protocol_serialization")]
     pub host: Protocol<'static>,
@@ -12,6 +12,22 @@ pub struct IpfsNodePublicAddress {
     pub peer_id: Protocol<'static>,
 }
 
+impl IpfsNodePublicAddress {
+    /// Returns the address under which the node can be peered with,
+    /// e.g. `/ip4/127.0.0.1/udp/4001/quic/p2p/12D3KooWL3EovpbdH1Axsk51xv9ascEsv9a81BuQdSZyNDtRSaHu`.
+    pub fn to_swarm_multiaddr(&self) -> Multiaddr {
+        let mut multiaddr = Multiaddr::empty();
+        multiaddr.push(self.host.clone());
+
+        for protocol in self.swarm_port.iter() {
+            multiaddr.push(protocol);
+        }
+
+        multiaddr.push(self.peer_id.clone());
+        multiaddr
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct IpfsNodeManagementAddress {
     pub host: String,
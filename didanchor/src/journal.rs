@@ -0,0 +1,491 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use identity_iota_client::document::ResolvedIotaDocument;
+use iota_client::block::output::AliasId;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{fs_sync::sync_parent_dir, DIDIndex};
+
+/// An update that was accepted by the anchor but not yet committed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JournaledUpdate {
+    pub document: ResolvedIotaDocument,
+    pub accepted_at: SystemTime,
+}
+
+/// A record in the [`Journal`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalRecord {
+    /// An update was accepted.
+    Update(JournaledUpdate),
+    /// A commit of all preceding updates was started.
+    CommitStarted,
+    /// Content was pinned by the current commit.
+    Pinned { cid: String },
+    /// The current commit is about to anchor the index with `index_cid`,
+    /// which no longer references the `superseded` content.
+    IndexPublished {
+        index_cid: String,
+        superseded: Vec<String>,
+    },
+    /// The current commit was anchored in the Alias Output with `alias_id`.
+    CommitPublished { alias_id: AliasId },
+    /// The current commit failed and its pins were removed. Its updates are pending again.
+    CommitAborted,
+}
+
+/// What needs to happen on startup to bring the anchor back in step with the journal.
+#[derive(Debug, Default)]
+pub struct JournalRecovery {
+    /// The updates that are not anchored, in the order they were accepted.
+    pub pending: Vec<JournaledUpdate>,
+    /// Content superseded by an anchored commit that was not handed to the garbage collector yet.
+    pub superseded: Vec<String>,
+    /// Content pinned by a commit that was never anchored.
+    pub orphaned: Vec<String>,
+}
+
+/// A write-ahead log of the uncommitted updates of an anchor and the progress of its commits.
+///
+/// Every record is flushed to disk before the operation it describes is considered done,
+/// so that accepted updates survive a crash and an interrupted commit can be resumed.
+/// Once a commit is done, the journal is compacted down to the updates that remain pending.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    records: Vec<JournalRecord>,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, creating it if it doesn't exist.
+    pub async fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path: PathBuf = path.as_ref().to_owned();
+
+        log::debug!("opening journal at {}", path.display());
+
+        let records: Vec<JournalRecord> = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => Self::parse(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(anyhow::anyhow!(err)),
+        };
+
+        let file: File = Self::open_append(&path).await?;
+
+        Ok(Self {
+            path,
+            file,
+            records,
+        })
+    }
+
+    /// Returns the alias id of the last commit that was published, if the journal still contains it.
+    pub fn published_alias_id(&self) -> Option<AliasId> {
+        self.records.iter().rev().find_map(|record| match record {
+            JournalRecord::CommitPublished { alias_id } => Some(*alias_id),
+            _ => None,
+        })
+    }
+
+    /// Determines which updates are still pending and which content needs to be unpinned,
+    /// given the index that is currently anchored in the ledger.
+    pub fn recover(
+        &self,
+        ledger_index_cid: Option<&str>,
+        ledger_index: &DIDIndex,
+    ) -> JournalRecovery {
+        let mut pending: Vec<JournaledUpdate> = Vec::new();
+        let mut superseded: Vec<String> = Vec::new();
+        let mut commit: Option<InterruptedCommit> = None;
+
+        for record in self.records.iter().cloned() {
+            match record {
+                JournalRecord::Update(update) => pending.push(update),
+                JournalRecord::CommitStarted => {
+                    let mut updates: Vec<JournaledUpdate> = Vec::new();
+                    let mut pinned: Vec<String> = Vec::new();
+
+                    match commit.take() {
+                        // A published commit whose journal was not compacted only misses its clean-up.
+                        Some(previous) if previous.published => {
+                            previous.finish(&mut superseded);
+                        }
+                        // A commit that was interrupted before it was published is retried by the next one.
+                        Some(previous) => {
+                            updates = previous.updates;
+                            pinned = previous.pinned;
+                        }
+                        None => (),
+                    }
+                    updates.append(&mut pending);
+
+                    commit = Some(InterruptedCommit {
+                        updates,
+                        pinned,
+                        ..Default::default()
+                    });
+                }
+                JournalRecord::Pinned { cid } => {
+                    if let Some(commit) = commit.as_mut() {
+                        commit.pinned.push(cid);
+                    }
+                }
+                JournalRecord::IndexPublished {
+                    index_cid,
+                    superseded,
+                } => {
+                    if let Some(commit) = commit.as_mut() {
+                        commit.index = Some((index_cid, superseded));
+                    }
+                }
+                JournalRecord::CommitPublished { .. } => {
+                    if let Some(commit) = commit.as_mut() {
+                        commit.published = true;
+                    }
+                }
+                JournalRecord::CommitAborted => {
+                    if let Some(commit) = commit.take() {
+                        let mut updates: Vec<JournaledUpdate> = commit.updates;
+                        updates.append(&mut pending);
+                        pending = updates;
+                    }
+                }
+            }
+        }
+
+        let commit: InterruptedCommit = match commit {
+            Some(commit) => commit,
+            None => {
+                return JournalRecovery {
+                    pending,
+                    superseded,
+                    orphaned: Vec::new(),
+                }
+            }
+        };
+
+        let anchored: bool = match commit.index {
+            Some((ref index_cid, _)) => {
+                commit.published || Some(index_cid.as_str()) == ledger_index_cid
+            }
+            None => false,
+        };
+
+        match commit {
+            // The commit was anchored, only the clean-up of the superseded content is left.
+            commit if anchored => {
+                commit.finish(&mut superseded);
+
+                JournalRecovery {
+                    pending,
+                    superseded,
+                    orphaned: Vec::new(),
+                }
+            }
+            // The commit was not anchored, so its updates are still pending.
+            commit => {
+                let referenced: HashSet<&str> = ledger_index
+                    .values()
+                    .map(String::as_str)
+                    .chain(ledger_index_cid)
+                    .collect();
+
+                let mut updates: Vec<JournaledUpdate> = commit.updates;
+                updates.append(&mut pending);
+
+                JournalRecovery {
+                    pending: updates,
+                    superseded,
+                    orphaned: commit
+                        .pinned
+                        .into_iter()
+                        .filter(|cid| !referenced.contains(cid.as_str()))
+                        .collect(),
+                }
+            }
+        }
+    }
+
+    /// Appends `record` to the journal and flushes it to disk.
+    pub async fn append(&mut self, record: &JournalRecord) -> anyhow::Result<()> {
+        self.append_all(std::slice::from_ref(record)).await
+    }
+
+    /// Appends `records` to the journal and flushes them to disk at once.
+    pub async fn append_all(&mut self, records: &[JournalRecord]) -> anyhow::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines: Vec<u8> = Vec::new();
+
+        for record in records {
+            serde_json::to_writer(&mut lines, record)?;
+            lines.push(b'\n');
+        }
+
+        self.file.write_all(&lines).await?;
+        self.file.sync_data().await?;
+
+        Ok(())
+    }
+
+    /// Atomically replaces the content of the journal with `updates`.
+    pub async fn compact(&mut self, updates: &[JournaledUpdate]) -> anyhow::Result<()> {
+        let mut content: Vec<u8> = Vec::new();
+
+        for update in updates {
+            serde_json::to_writer(&mut content, &JournalRecord::Update(update.clone()))?;
+            content.push(b'\n');
+        }
+
+        let tmp_path: PathBuf = self.path.with_extension("tmp");
+
+        let mut tmp_file: File = File::create(&tmp_path).await?;
+        tmp_file.write_all(&content).await?;
+        tmp_file.sync_all().await?;
+
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        sync_parent_dir(&self.path).await?;
+
+        self.file = Self::open_append(&self.path).await?;
+        self.records.clear();
+
+        Ok(())
+    }
+
+    fn parse(content: &str) -> anyhow::Result<Vec<JournalRecord>> {
+        let lines: Vec<&str> = content.lines().filter(|line| !line.is_empty()).collect();
+        let mut records: Vec<JournalRecord> = Vec::with_capacity(lines.len());
+
+        for (i, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                // The last record might have been torn by a crash while it was written,
+                // in which case the operation it describes has not happened.
+                Err(err) if i + 1 == lines.len() => {
+                    log::warn!("ignoring incomplete last journal record: {err}");
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| format!("journal record {i} is corrupt"));
+                }
+            }
+        }
+
+        Ok(records)
+    }
+
+    async fn open_append(path: &Path) -> anyhow::Result<File> {
+        Ok(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?)
+    }
+}
+
+#[derive(Default)]
+struct InterruptedCommit {
+    updates: Vec<JournaledUpdate>,
+    pinned: Vec<String>,
+    index: Option<(String, Vec<String>)>,
+    published: bool,
+}
+
+impl InterruptedCommit {
+    /// Hands the content superseded by this anchored commit over to `superseded`,
+    /// minus the content it references again.
+    fn finish(self, superseded: &mut Vec<String>) {
+        let pinned: HashSet<String> = self.pinned.into_iter().collect();
+        superseded.retain(|cid| !pinned.contains(cid));
+
+        if let Some((_, commit_superseded)) = self.index {
+            superseded.extend(commit_superseded);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use identity_core::crypto::{KeyPair, KeyType};
+    use identity_iota_client::document::ResolvedIotaDocument;
+    use identity_iota_core::document::IotaDocument;
+    use iota_client::block::output::AliasId;
+
+    use super::{Journal, JournalRecord, JournaledUpdate};
+    use crate::DIDIndex;
+
+    fn update() -> JournaledUpdate {
+        let keypair: KeyPair = KeyPair::new(KeyType::Ed25519).unwrap();
+        let document: IotaDocument = IotaDocument::new(&keypair).unwrap();
+
+        JournaledUpdate {
+            document: ResolvedIotaDocument::from(document),
+            accepted_at: SystemTime::now(),
+        }
+    }
+
+    async fn journal(records: &[JournalRecord]) -> Journal {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("journal-{}.jsonl", rand::random::<u64>()));
+
+        let mut journal = Journal::open(&path).await.unwrap();
+
+        for record in records {
+            journal.append(record).await.unwrap();
+        }
+
+        // Reopen to read the records back from disk.
+        Journal::open(&path).await.unwrap()
+    }
+
+    fn pinned(cid: &str) -> JournalRecord {
+        JournalRecord::Pinned {
+            cid: cid.to_owned(),
+        }
+    }
+
+    fn index_published(index_cid: &str, superseded: &[&str]) -> JournalRecord {
+        JournalRecord::IndexPublished {
+            index_cid: index_cid.to_owned(),
+            superseded: superseded.iter().map(|cid| cid.to_string()).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_journal_recover_pending_updates() {
+        let journal = journal(&[
+            JournalRecord::Update(update()),
+            JournalRecord::Update(update()),
+        ])
+        .await;
+
+        let recovery = journal.recover(None, &DIDIndex::new());
+
+        assert_eq!(recovery.pending.len(), 2);
+        assert!(recovery.orphaned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_recover_unpublished_commit() {
+        let journal = journal(&[
+            JournalRecord::Update(update()),
+            JournalRecord::CommitStarted,
+            pinned("chain"),
+            pinned("new-index"),
+            index_published("new-index", &["old-chain", "old-index"]),
+        ])
+        .await;
+
+        // The ledger still references the old index, so the commit has to be redone.
+        let recovery = journal.recover(Some("old-index"), &DIDIndex::new());
+
+        assert_eq!(recovery.pending.len(), 1);
+        assert_eq!(recovery.orphaned, vec!["chain", "new-index"]);
+        assert!(recovery.superseded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_recover_anchored_commit() {
+        let journal = journal(&[
+            JournalRecord::Update(update()),
+            JournalRecord::CommitStarted,
+            pinned("chain"),
+            pinned("new-index"),
+            index_published("new-index", &["old-chain", "old-index"]),
+        ])
+        .await;
+
+        // The ledger references the new index, so only the unpinning is left.
+        let recovery = journal.recover(Some("new-index"), &DIDIndex::new());
+
+        assert!(recovery.pending.is_empty());
+        assert_eq!(recovery.superseded, vec!["old-chain", "old-index"]);
+        assert!(recovery.orphaned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_recover_commit_after_uncompacted_commit() {
+        let journal = journal(&[
+            JournalRecord::Update(update()),
+            JournalRecord::CommitStarted,
+            pinned("chain"),
+            pinned("index"),
+            index_published("index", &["old-chain", "old-index"]),
+            JournalRecord::CommitPublished {
+                alias_id: AliasId::null(),
+            },
+            // Compacting the journal failed, so the next commit was journaled after the first one.
+            JournalRecord::Update(update()),
+            JournalRecord::CommitStarted,
+            pinned("new-chain"),
+        ])
+        .await;
+
+        // The second commit never made it to the ledger.
+        let recovery = journal.recover(Some("index"), &DIDIndex::new());
+
+        // Only the update of the second commit is pending again.
+        assert_eq!(recovery.pending.len(), 1);
+        assert_eq!(recovery.superseded, vec!["old-chain", "old-index"]);
+        assert_eq!(recovery.orphaned, vec!["new-chain"]);
+    }
+
+    #[tokio::test]
+    async fn test_journal_recover_aborted_commit() {
+        let journal = journal(&[
+            JournalRecord::Update(update()),
+            JournalRecord::CommitStarted,
+            pinned("chain"),
+            JournalRecord::CommitAborted,
+            JournalRecord::Update(update()),
+        ])
+        .await;
+
+        // The pins of an aborted commit were already removed.
+        let recovery = journal.recover(None, &DIDIndex::new());
+
+        assert_eq!(recovery.pending.len(), 2);
+        assert!(recovery.orphaned.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_compact_and_torn_record() {
+        let mut journal = journal(&[
+            JournalRecord::Update(update()),
+            JournalRecord::CommitStarted,
+            pinned("chain"),
+        ])
+        .await;
+
+        let recovery = journal.recover(None, &DIDIndex::new());
+        journal.compact(&recovery.pending).await.unwrap();
+
+        // Simulate a crash while writing a record.
+        tokio::fs::write(
+            &journal.path,
+            format!(
+                "{}{{\"type\":\"upd",
+                tokio::fs::read_to_string(&journal.path).await.unwrap()
+            ),
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::open(&journal.path).await.unwrap();
+
+        // Recovering again yields the same result.
+        assert_eq!(journal.recover(None, &DIDIndex::new()).pending.len(), 1);
+        assert!(journal.recover(None, &DIDIndex::new()).orphaned.is_empty());
+    }
+}
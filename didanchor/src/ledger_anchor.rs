@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use iota_client::block::output::{AliasId, AliasOutput, Output, OutputId};
+
+use crate::{
+    resolve_alias_output_response, AliasContent, AliasHistory, AnchorOutput, LedgerReceipt,
+};
+
+/// A state of an alias together with its content.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AliasState {
+    pub state_index: u32,
+    pub content: AliasContent,
+}
+
+/// A ledger in which the content of an alias is anchored.
+#[async_trait::async_trait]
+pub trait LedgerAnchor: Send + Sync {
+    /// Publishes `content` as the next state of the alias with `alias_id`
+    /// and returns where it was anchored. A null `alias_id` creates a new alias.
+    async fn publish_content(
+        &mut self,
+        alias_id: AliasId,
+        content: AliasContent,
+    ) -> anyhow::Result<LedgerReceipt>;
+
+    /// Returns the current content of the alias, or `None` if it doesn't exist.
+    async fn read_content(&self, alias_id: AliasId) -> anyhow::Result<Option<AliasContent>>;
+
+    /// Returns the states of the alias from the oldest to the current one.
+    async fn read_history(&self, alias_id: AliasId) -> anyhow::Result<Vec<AliasState>>;
+
+    /// Returns the index of the current state of the alias, or `None` if it doesn't exist.
+    async fn state_index(&self, alias_id: AliasId) -> anyhow::Result<Option<u32>>;
+}
+
+#[async_trait::async_trait]
+impl LedgerAnchor for AnchorOutput {
+    async fn publish_content(
+        &mut self,
+        alias_id: AliasId,
+        content: AliasContent,
+    ) -> anyhow::Result<LedgerReceipt> {
+        self.alias_id = alias_id;
+        self.publish_output(content).await
+    }
+
+    async fn read_content(&self, alias_id: AliasId) -> anyhow::Result<Option<AliasContent>> {
+        Ok(self
+            .read_alias_output(alias_id)
+            .await?
+            .map(|(_, alias_output)| {
+                AliasContent::from_state_metadata(alias_output.state_metadata())
+                    .map(|(content, _)| content)
+            })
+            .transpose()?)
+    }
+
+    async fn read_history(&self, alias_id: AliasId) -> anyhow::Result<Vec<AliasState>> {
+        Ok(AliasHistory::new(self.client.clone())
+            .states(alias_id)
+            .await?
+            .into_iter()
+            .map(|state| AliasState {
+                state_index: state.state_index,
+                content: state.content,
+            })
+            .collect())
+    }
+
+    async fn state_index(&self, alias_id: AliasId) -> anyhow::Result<Option<u32>> {
+        Ok(self
+            .read_alias_output(alias_id)
+            .await?
+            .map(|(_, alias_output)| alias_output.state_index()))
+    }
+}
+
+impl AnchorOutput {
+    async fn read_alias_output(
+        &self,
+        alias_id: AliasId,
+    ) -> anyhow::Result<Option<(OutputId, AliasOutput)>> {
+        if alias_id.is_null() {
+            return Ok(None);
+        }
+
+        match resolve_alias_output_response(&self.client, alias_id).await? {
+            Some((output_id, output_response)) => {
+                match Output::try_from(&output_response.output)? {
+                    Output::Alias(alias_output) => Ok(Some((output_id, alias_output))),
+                    _ => anyhow::bail!("output {output_id} is not an alias output"),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// The states of every alias in a [`MockLedger`] or [`FileLedger`].
+type AliasStates = HashMap<AliasId, Vec<AliasContent>>;
+
+/// A ledger that only exists in memory, e.g. for tests.
+///
+/// Clones share the same ledger, so that content published by an anchor can be read by a resolver.
+#[derive(Debug, Clone, Default)]
+pub struct MockLedger {
+    aliases: Arc<Mutex<AliasStates>>,
+}
+
+impl MockLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_aliases<T>(&self, f: impl FnOnce(&mut AliasStates) -> T) -> T {
+        f(&mut self
+            .aliases
+            .lock()
+            .expect("the lock should not be poisoned"))
+    }
+}
+
+#[async_trait::async_trait]
+impl LedgerAnchor for MockLedger {
+    async fn publish_content(
+        &mut self,
+        alias_id: AliasId,
+        content: AliasContent,
+    ) -> anyhow::Result<LedgerReceipt> {
+        self.with_aliases(|aliases| publish_state(aliases, alias_id, content))
+    }
+
+    async fn read_content(&self, alias_id: AliasId) -> anyhow::Result<Option<AliasContent>> {
+        Ok(self.with_aliases(|aliases| {
+            aliases
+                .get(&alias_id)
+                .and_then(|states| states.last().cloned())
+        }))
+    }
+
+    async fn read_history(&self, alias_id: AliasId) -> anyhow::Result<Vec<AliasState>> {
+        Ok(self.with_aliases(|aliases| {
+            aliases
+                .get(&alias_id)
+                .map(|states| {
+                    states
+                        .iter()
+                        .enumerate()
+                        .map(|(state_index, content)| AliasState {
+                            state_index: state_index as u32,
+                            content: content.clone(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        }))
+    }
+
+    async fn state_index(&self, alias_id: AliasId) -> anyhow::Result<Option<u32>> {
+        Ok(self
+            .with_aliases(|aliases| aliases.get(&alias_id).map(|states| states.len() as u32 - 1)))
+    }
+}
+
+/// Appends `content` as the next state of the alias, or creates a new alias if `alias_id` is null.
+fn publish_state(
+    aliases: &mut AliasStates,
+    alias_id: AliasId,
+    content: AliasContent,
+) -> anyhow::Result<LedgerReceipt> {
+    let alias_id: AliasId = if alias_id.is_null() {
+        let alias_id = AliasId::new(rand::random());
+        aliases.insert(alias_id, Vec::new());
+        alias_id
+    } else {
+        alias_id
+    };
+
+    let states: &mut Vec<AliasContent> = aliases
+        .get_mut(&alias_id)
+        .ok_or_else(|| anyhow::anyhow!("no output found for alias id {alias_id}"))?;
+    states.push(content);
+
+    Ok(LedgerReceipt::new(alias_id, states.len() as u32 - 1))
+}
+
+/// A ledger that is persisted to a local JSON file, e.g. for development without a network.
+#[derive(Debug, Clone)]
+pub struct FileLedger {
+    path: PathBuf,
+    ledger: MockLedger,
+}
+
+impl FileLedger {
+    /// Opens the ledger at `path`, which is created on the first publish if it doesn't exist.
+    pub async fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path: PathBuf = path.as_ref().to_owned();
+
+        let aliases: AliasStates = match tokio::fs::read(&path).await {
+            Ok(content) => serde_json::from_slice::<Vec<(AliasId, Vec<AliasContent>)>>(&content)?
+                .into_iter()
+                .collect(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => AliasStates::new(),
+            Err(err) => return Err(anyhow::anyhow!(err)),
+        };
+
+        Ok(Self {
+            path,
+            ledger: MockLedger {
+                aliases: Arc::new(Mutex::new(aliases)),
+            },
+        })
+    }
+
+    async fn save(&self, aliases: &AliasStates) -> anyhow::Result<()> {
+        let aliases: Vec<(&AliasId, &Vec<AliasContent>)> = aliases.iter().collect();
+
+        let tmp_path: PathBuf = self.path.with_extension("tmp");
+
+        tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(&aliases)?).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl LedgerAnchor for FileLedger {
+    async fn publish_content(
+        &mut self,
+        alias_id: AliasId,
+        content: AliasContent,
+    ) -> anyhow::Result<LedgerReceipt> {
+        // The new state is only visible once it is persisted, so that a failed save doesn't publish it.
+        let mut aliases: AliasStates = self.ledger.with_aliases(|aliases| aliases.clone());
+        let receipt: LedgerReceipt = publish_state(&mut aliases, alias_id, content)?;
+
+        self.save(&aliases).await?;
+        self.ledger.with_aliases(|current| *current = aliases);
+
+        Ok(receipt)
+    }
+
+    async fn read_content(&self, alias_id: AliasId) -> anyhow::Result<Option<AliasContent>> {
+        self.ledger.read_content(alias_id).await
+    }
+
+    async fn read_history(&self, alias_id: AliasId) -> anyhow::Result<Vec<AliasState>> {
+        self.ledger.read_history(alias_id).await
+    }
+
+    async fn state_index(&self, alias_id: AliasId) -> anyhow::Result<Option<u32>> {
+        self.ledger.state_index(alias_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iota_client::block::output::AliasId;
+
+    use super::{FileLedger, LedgerAnchor, MockLedger};
+    use crate::AliasContent;
+
+    fn content(index_cid: &str) -> AliasContent {
+        AliasContent::new(index_cid.to_owned(), Vec::new(), vec![0; 32])
+    }
+
+    #[tokio::test]
+    async fn test_mock_ledger() {
+        let mut ledger = MockLedger::new();
+        let reader = ledger.clone();
+
+        assert!(reader
+            .read_content(AliasId::null())
+            .await
+            .unwrap()
+            .is_none());
+
+        let alias_id = ledger
+            .publish_content(AliasId::null(), content("a"))
+            .await
+            .unwrap()
+            .alias_id;
+        assert!(!alias_id.is_null());
+
+        let receipt = ledger
+            .publish_content(alias_id, content("b"))
+            .await
+            .unwrap();
+        assert_eq!(receipt.state_index, 1);
+
+        assert_eq!(
+            reader.read_content(alias_id).await.unwrap(),
+            Some(content("b"))
+        );
+        assert_eq!(reader.state_index(alias_id).await.unwrap(), Some(1));
+
+        let history = reader.read_history(alias_id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].content, content("a"));
+        assert_eq!(history[1].state_index, 1);
+
+        assert!(ledger
+            .publish_content(AliasId::new([1; 32]), content("c"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_ledger_persists_content() {
+        let path = std::env::temp_dir().join(format!("ledger-{}.json", rand::random::<u64>()));
+
+        let mut ledger = FileLedger::open(&path).await.unwrap();
+        let alias_id = ledger
+            .publish_content(AliasId::null(), content("a"))
+            .await
+            .unwrap()
+            .alias_id;
+
+        let ledger = FileLedger::open(&path).await.unwrap();
+        assert_eq!(
+            ledger.read_content(alias_id).await.unwrap(),
+            Some(content("a"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_ledger_does_not_publish_unsaved_content() {
+        let path = std::env::temp_dir()
+            .join(format!("missing-{}", rand::random::<u64>()))
+            .join("ledger.json");
+
+        let mut ledger = FileLedger::open(&path).await.unwrap();
+        assert!(ledger
+            .publish_content(AliasId::null(), content("a"))
+            .await
+            .is_err());
+        assert!(ledger.ledger.with_aliases(|aliases| aliases.is_empty()));
+    }
+}
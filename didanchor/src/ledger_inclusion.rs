@@ -0,0 +1,237 @@
+use crypto::hashes::blake2b::Blake2b256;
+use iota_client::block::{
+    dto::BlockDto,
+    output::{AliasId, AliasOutput, Output, OutputId},
+    payload::{
+        dto::MilestonePayloadDto,
+        milestone::{MilestoneIndex, MilestonePayload},
+        transaction::TransactionEssence,
+        Payload,
+    },
+    Block, BlockId,
+};
+use merkle_tree::DigestExt;
+use reqwest::Client;
+use url::Url;
+
+use crate::MilestoneCommittee;
+
+/// A proof that a block was referenced by a milestone and thus included in the ledger.
+///
+/// Obtained from the proof-of-inclusion plugin of a node and verifiable without access to the network.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LedgerInclusionProof {
+    /// The milestone that referenced the block.
+    pub milestone: MilestonePayloadDto,
+    /// The included block.
+    pub block: BlockDto,
+    /// The Merkle audit path from the block id to the milestone's inclusion merkle root.
+    #[serde(rename = "proof")]
+    pub audit_path: AuditPath,
+}
+
+impl LedgerInclusionProof {
+    /// Requests a proof of inclusion for the given `block_id` from the node at `iota_endpoint`.
+    ///
+    /// <https://wiki.iota.org/shimmer/inx-poi/references/api_reference/>
+    pub async fn fetch(
+        client: &Client,
+        iota_endpoint: &Url,
+        block_id: &BlockId,
+    ) -> anyhow::Result<Self> {
+        let endpoint: Url = iota_endpoint.join(&format!("api/poi/v1/create/{block_id}"))?;
+
+        log::debug!("requesting proof of inclusion for block {block_id}");
+
+        let response = client.get(endpoint).send().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "failed to create proof of inclusion for block {block_id} with status {}",
+                response.status()
+            );
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Returns the included block.
+    pub fn block(&self) -> anyhow::Result<Block> {
+        Ok(Block::try_from(&self.block)?)
+    }
+
+    /// Returns the milestone that referenced the block.
+    pub fn milestone(&self) -> anyhow::Result<MilestonePayload> {
+        Ok(MilestonePayload::try_from(&self.milestone)?)
+    }
+
+    /// Returns the Alias Output with `output_id` from the transaction of the included block
+    /// and makes sure it belongs to `alias_id`.
+    pub fn alias_output(
+        &self,
+        output_id: &OutputId,
+        alias_id: &AliasId,
+    ) -> anyhow::Result<AliasOutput> {
+        let block: Block = self.block()?;
+
+        let transaction = match block.payload() {
+            Some(Payload::Transaction(transaction)) => transaction,
+            _ => anyhow::bail!("the included block does not contain a transaction"),
+        };
+
+        if transaction.id() != *output_id.transaction_id() {
+            anyhow::bail!("output {output_id} was not created by the included transaction");
+        }
+
+        let TransactionEssence::Regular(essence) = transaction.essence();
+
+        let alias_output: &AliasOutput = match essence.outputs().get(output_id.index() as usize) {
+            Some(Output::Alias(alias_output)) => alias_output,
+            Some(_) => anyhow::bail!("output {output_id} is not an alias output"),
+            None => anyhow::bail!("output {output_id} is missing in the transaction"),
+        };
+
+        let output_alias_id: AliasId = if alias_output.alias_id().is_null() {
+            AliasId::from(*output_id)
+        } else {
+            alias_output.alias_id().to_owned()
+        };
+
+        if output_alias_id != *alias_id {
+            anyhow::bail!("output {output_id} does not belong to alias {alias_id}");
+        }
+
+        Ok(alias_output.to_owned())
+    }
+
+    /// Verifies that the audit path contains the block and leads to the inclusion merkle root
+    /// of the milestone, and that the milestone is signed by the given `committee`.
+    pub fn verify_with_committee(
+        &self,
+        committee: &MilestoneCommittee,
+    ) -> anyhow::Result<MilestoneIndex> {
+        let milestone_index: MilestoneIndex = self.verify()?;
+
+        committee.verify(&self.milestone()?)?;
+
+        Ok(milestone_index)
+    }
+
+    /// Verifies that the audit path contains the block and leads to the inclusion merkle root
+    /// of the milestone.
+    ///
+    /// This does not verify the milestone signatures, see [`Self::verify_with_committee`].
+    pub fn verify(&self) -> anyhow::Result<MilestoneIndex> {
+        let block_id: BlockId = self.block()?.id();
+        let milestone: MilestonePayload = self.milestone()?;
+
+        if !self.audit_path.contains_value(block_id.as_ref()) {
+            anyhow::bail!("the audit path does not contain block {block_id}");
+        }
+
+        let root: Vec<u8> = self.audit_path.root(&mut Blake2b256::new())?;
+
+        if root.as_slice() != milestone.essence().inclusion_merkle_root().as_ref() {
+            anyhow::bail!(
+                "the audit path does not lead to the inclusion merkle root of milestone {}",
+                milestone.essence().index()
+            );
+        }
+
+        Ok(milestone.essence().index())
+    }
+}
+
+/// A node in the Merkle audit path of a [`LedgerInclusionProof`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum AuditPath {
+    /// An inner node with its left and right subtree.
+    Node {
+        l: Box<AuditPath>,
+        r: Box<AuditPath>,
+    },
+    /// The pre-computed hash of a subtree that is not on the path.
+    Hash { h: String },
+    /// The leaf whose inclusion is proven, i.e. a block id.
+    Value { value: String },
+}
+
+impl AuditPath {
+    /// Computes the root hash of the audit path.
+    pub fn root(&self, digest: &mut Blake2b256) -> anyhow::Result<Vec<u8>> {
+        match self {
+            AuditPath::Node { l, r } => {
+                let lhs = l.root(digest)?;
+                let rhs = r.root(digest)?;
+
+                let lhs = crypto::hashes::Output::<Blake2b256>::from_exact_iter(lhs)
+                    .ok_or_else(|| anyhow::anyhow!("invalid hash length in audit path"))?;
+                let rhs = crypto::hashes::Output::<Blake2b256>::from_exact_iter(rhs)
+                    .ok_or_else(|| anyhow::anyhow!("invalid hash length in audit path"))?;
+
+                Ok(digest.hash_node(&lhs, &rhs).to_vec())
+            }
+            AuditPath::Hash { h } => prefix_hex::decode::<Vec<u8>>(h)
+                .map_err(|err| anyhow::anyhow!("invalid hash in audit path: {err:?}")),
+            AuditPath::Value { value } => {
+                let value: Vec<u8> = prefix_hex::decode(value)
+                    .map_err(|err| anyhow::anyhow!("invalid value in audit path: {err:?}"))?;
+
+                Ok(digest.hash_leaf(&value).to_vec())
+            }
+        }
+    }
+
+    /// Returns whether the audit path proves the inclusion of `value`.
+    pub fn contains_value(&self, value: &[u8]) -> bool {
+        match self {
+            AuditPath::Node { l, r } => l.contains_value(value) || r.contains_value(value),
+            AuditPath::Hash { .. } => false,
+            AuditPath::Value { value: own_value } => prefix_hex::decode::<Vec<u8>>(own_value)
+                .map(|own_value| own_value == value)
+                .unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto::hashes::blake2b::Blake2b256;
+    use merkle_tree::DigestExt;
+
+    use super::AuditPath;
+
+    #[test]
+    fn test_audit_path_root() {
+        let mut digest = Blake2b256::new();
+
+        let value: [u8; 32] = rand::random();
+        let sibling: [u8; 32] = rand::random();
+        let uncle: [u8; 32] = rand::random();
+
+        let value_hash = digest.hash_leaf(&value);
+        let sibling_hash = digest.hash_leaf(&sibling);
+        let uncle_hash = digest.hash_leaf(&uncle);
+        let parent = digest.hash_node(&sibling_hash, &value_hash);
+        let root = digest.hash_node(&parent, &uncle_hash);
+
+        let audit_path = AuditPath::Node {
+            l: Box::new(AuditPath::Node {
+                l: Box::new(AuditPath::Hash {
+                    h: prefix_hex::encode(sibling_hash.to_vec()),
+                }),
+                r: Box::new(AuditPath::Value {
+                    value: prefix_hex::encode(value.to_vec()),
+                }),
+            }),
+            r: Box::new(AuditPath::Hash {
+                h: prefix_hex::encode(uncle_hash.to_vec()),
+            }),
+        };
+
+        assert_eq!(audit_path.root(&mut digest).unwrap(), root.to_vec());
+        assert!(audit_path.contains_value(&value));
+        assert!(!audit_path.contains_value(&sibling));
+    }
+}
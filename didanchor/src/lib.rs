@@ -1,19 +1,55 @@
 mod alias_content;
+mod alias_history;
 mod anchor;
 mod anchor_config;
 mod anchor_output;
+mod audit;
+mod background_task;
 mod chain_storage;
+mod commit_receipt;
+mod committer;
+mod content_store;
+mod evidence;
+mod external_signer;
+mod fs_sync;
+mod governance;
 mod ipfs_gateway;
 mod ipfs_node_addr;
+mod journal;
+mod ledger_anchor;
+mod ledger_inclusion;
 mod merkle_dids;
+mod milestone_committee;
+mod mirror;
+mod pin_gc;
 mod resolver;
+mod secret_source;
+mod storage_deposit;
+mod watcher;
 
 pub use alias_content::*;
+pub use alias_history::*;
 pub use anchor::*;
 pub use anchor_config::*;
 pub use anchor_output::*;
+pub use audit::*;
 pub use chain_storage::*;
+pub use commit_receipt::*;
+pub use committer::*;
+pub use content_store::*;
+pub use evidence::*;
+pub use external_signer::*;
+pub use governance::*;
 pub use ipfs_gateway::*;
 pub use ipfs_node_addr::*;
+pub use journal::*;
+pub use ledger_anchor::*;
+pub use ledger_inclusion::*;
 pub use merkle_dids::*;
+pub use milestone_committee::*;
+pub use mirror::*;
+pub use pin_gc::*;
 pub use resolver::*;
+pub use secret_source::*;
+pub use storage_deposit::*;
+pub use watcher::*;
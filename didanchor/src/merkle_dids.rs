@@ -12,6 +12,21 @@ pub struct MerkleDIDs {
     document_tree: HashMap<IotaDID, usize>,
 }
 
+/// An update of a chain of custody that was validated, but not yet applied to the tree.
+pub struct ValidatedUpdate {
+    did: IotaDID,
+    chain_of_custody: ChainOfCustody,
+    serialized: Vec<u8>,
+    // The leaf of the chain, or `None` if the document is a new root document.
+    leaf_index: Option<usize>,
+}
+
+impl ValidatedUpdate {
+    pub fn did(&self) -> &IotaDID {
+        &self.did
+    }
+}
+
 impl MerkleDIDs {
     pub fn new() -> Self {
         Self {
@@ -20,20 +35,62 @@ impl MerkleDIDs {
         }
     }
 
+    /// Rebuilds the tree from anchored chains of custody, each given with the leaf index of its proof.
+    ///
+    /// Fails unless the chains cover every leaf of the tree exactly once.
+    pub fn from_chains(mut chains: Vec<(IotaDID, usize, ChainOfCustody)>) -> anyhow::Result<Self> {
+        chains.sort_by_key(|(_, leaf_index, _)| *leaf_index);
+
+        let mut merkle_dids = Self::new();
+
+        for (did, leaf_index, chain_of_custody) in chains {
+            if leaf_index != merkle_dids.document_tree.len() {
+                anyhow::bail!(
+                    "the chain of custody of {did} is at leaf {leaf_index}, expected leaf {}",
+                    merkle_dids.document_tree.len()
+                );
+            }
+
+            let serialized: Vec<u8> = chain_of_custody.serialize_to_vec()?;
+            merkle_dids.merkle_tree.push(serialized);
+            merkle_dids.document_tree.insert(did, leaf_index);
+        }
+
+        Ok(merkle_dids)
+    }
+
     /// Updates the document or inserts it if it doesn't exist.
     pub fn update_document(
         &mut self,
         chain_of_custody: Option<ChainOfCustody>,
         document: ResolvedIotaDocument,
     ) -> anyhow::Result<ChainOfCustody> {
+        let update: ValidatedUpdate = self.validate_update(chain_of_custody, document)?;
+
+        Ok(self.apply_update(update))
+    }
+
+    /// Validates that `document` is a valid addition to `chain_of_custody`, or a valid root document
+    /// if there is no chain yet, without modifying the tree.
+    pub fn validate_update(
+        &self,
+        chain_of_custody: Option<ChainOfCustody>,
+        document: ResolvedIotaDocument,
+    ) -> anyhow::Result<ValidatedUpdate> {
+        let did: IotaDID = document.document.id().to_owned();
+
         match chain_of_custody {
             Some(mut chain_of_custody) => {
+                let leaf_index: usize = *self.document_tree.get(&did).ok_or_else(|| {
+                    anyhow::anyhow!("{did} has a chain of custody, but no leaf in the merkle tree")
+                })?;
+
                 let mut iterator = chain_of_custody.0.iter();
 
                 let mut chain = IntegrationChain::new(
                     iterator
                         .next()
-                        .expect("non-empty vectors should never be inserted")
+                        .ok_or_else(|| anyhow::anyhow!("the chain of custody of {did} is empty"))?
                         .to_owned(),
                 )?;
 
@@ -45,40 +102,46 @@ impl MerkleDIDs {
 
                 chain.check_valid_addition(&document)?;
 
-                let did: IotaDID = document.document.id().to_owned();
                 chain_of_custody.0.push(document);
 
-                // Update Merkle Tree.
                 // Serialize the entire chain of custody.
+                let serialized: Vec<u8> = chain_of_custody.serialize_to_vec()?;
 
-                let serialized = chain_of_custody.serialize_to_vec()?;
-
-                let index: usize = *self
-                    .document_tree
-                    .get(&did)
-                    .expect("the index should exist if a chain of custody exists");
-
-                self.merkle_tree.replace(index, serialized);
-
-                Ok(chain_of_custody)
+                Ok(ValidatedUpdate {
+                    did,
+                    chain_of_custody,
+                    serialized,
+                    leaf_index: Some(leaf_index),
+                })
             }
             None => {
                 // Make sure it's a valid root document.
                 IntegrationChain::new(document.clone())?;
 
-                let did: IotaDID = document.document.id().to_owned();
-
                 let chain_of_custody: ChainOfCustody = ChainOfCustody(vec![document]);
-
                 let serialized: Vec<u8> = chain_of_custody.serialize_to_vec()?;
 
-                let merkle_tree_index: usize = self.merkle_tree.push(serialized);
-
-                self.document_tree.insert(did, merkle_tree_index);
+                Ok(ValidatedUpdate {
+                    did,
+                    chain_of_custody,
+                    serialized,
+                    leaf_index: None,
+                })
+            }
+        }
+    }
 
-                Ok(chain_of_custody)
+    /// Applies a validated update to the tree and returns the updated chain of custody.
+    pub fn apply_update(&mut self, update: ValidatedUpdate) -> ChainOfCustody {
+        match update.leaf_index {
+            Some(leaf_index) => self.merkle_tree.replace(leaf_index, update.serialized),
+            None => {
+                let leaf_index: usize = self.merkle_tree.push(update.serialized);
+                self.document_tree.insert(update.did, leaf_index);
             }
         }
+
+        update.chain_of_custody
     }
 
     pub fn merkle_root(&self) -> Vec<u8> {
@@ -296,4 +359,50 @@ mod tests {
 
         assert!(document3_proof.verify(merkle_dids.merkle_root().as_ref(), coc_serialized))
     }
+
+    #[test]
+    fn test_merkle_dids_from_chains() {
+        let (_keypair1, document1) = gen_document();
+        let (_keypair2, document2) = gen_document();
+
+        let mut merkle_dids = MerkleDIDs::new();
+
+        let coc1 = merkle_dids
+            .update_document(None, document1.clone())
+            .unwrap();
+        let coc2 = merkle_dids
+            .update_document(None, document2.clone())
+            .unwrap();
+
+        let leaf_index = |document: &ResolvedIotaDocument| {
+            merkle_dids
+                .generate_merkle_proof(document.did())
+                .unwrap()
+                .index()
+        };
+
+        // The order of the chains doesn't matter, only the leaf indices of their proofs.
+        let rebuilt = MerkleDIDs::from_chains(vec![
+            (
+                document2.did().clone(),
+                leaf_index(&document2),
+                coc2.clone(),
+            ),
+            (
+                document1.did().clone(),
+                leaf_index(&document1),
+                coc1.clone(),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(rebuilt.merkle_root(), merkle_dids.merkle_root());
+
+        // A gap in the leaves means that a chain is missing.
+        assert!(MerkleDIDs::from_chains(vec![(document2.did().clone(), 1, coc2)]).is_err());
+
+        // A chain of custody whose DID has no leaf in the tree can't be updated.
+        assert!(MerkleDIDs::new()
+            .update_document(Some(coc1), document1)
+            .is_err());
+    }
 }
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use crypto::signatures::ed25519::{PublicKey, Signature as Ed25519Signature};
+use iota_client::block::{payload::milestone::MilestonePayload, signature::Signature};
+
+/// The set of keys that sign milestones of a network, as configured in the protocol parameters of its nodes.
+///
+/// Used to verify milestones without trusting the node that returned them.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MilestoneCommittee {
+    /// The minimum number of valid signatures from applicable keys a milestone needs.
+    pub min_threshold: usize,
+    /// The keys and the milestone ranges in which they are applicable.
+    pub key_ranges: Vec<MilestoneKeyRange>,
+}
+
+/// A milestone public key and the range of milestone indices in which it is applicable.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MilestoneKeyRange {
+    /// The prefix-hex encoded Ed25519 public key.
+    pub public_key: String,
+    /// The first milestone index at which the key is applicable.
+    pub start_index: u32,
+    /// The last milestone index at which the key is applicable, where `0` means it does not expire.
+    pub end_index: u32,
+}
+
+impl MilestoneCommittee {
+    pub fn new(min_threshold: usize, key_ranges: Vec<MilestoneKeyRange>) -> Self {
+        Self {
+            min_threshold,
+            key_ranges,
+        }
+    }
+
+    /// Verifies that `milestone` is signed by at least `min_threshold` of the keys
+    /// applicable at its index and that all of its signatures are valid.
+    pub fn verify(&self, milestone: &MilestonePayload) -> anyhow::Result<()> {
+        let index: u32 = *milestone.essence().index();
+        let essence_hash: [u8; 32] = milestone.essence().hash();
+
+        let signatures = milestone.signatures().iter().map(|signature| {
+            let Signature::Ed25519(signature) = signature;
+            (*signature.public_key(), *signature.signature())
+        });
+
+        self.verify_signatures(index, &essence_hash, signatures)
+    }
+
+    fn verify_signatures(
+        &self,
+        index: u32,
+        essence_hash: &[u8; 32],
+        signatures: impl Iterator<Item = ([u8; 32], [u8; 64])>,
+    ) -> anyhow::Result<()> {
+        let applicable_keys: HashSet<[u8; 32]> = self.applicable_public_keys(index)?;
+        let mut signers: HashSet<[u8; 32]> = HashSet::new();
+
+        for (public_key, signature) in signatures {
+            if !applicable_keys.contains(&public_key) {
+                anyhow::bail!(
+                    "milestone {index} is signed by {}, which is not an applicable key",
+                    prefix_hex::encode(public_key)
+                );
+            }
+
+            let verifying_key = PublicKey::try_from_bytes(public_key)
+                .map_err(|err| anyhow::anyhow!("invalid milestone public key: {err:?}"))?;
+
+            if !verifying_key.verify(&Ed25519Signature::from_bytes(signature), essence_hash) {
+                anyhow::bail!(
+                    "invalid signature of {} on milestone {index}",
+                    prefix_hex::encode(public_key)
+                );
+            }
+
+            signers.insert(public_key);
+        }
+
+        if signers.len() < self.min_threshold {
+            anyhow::bail!(
+                "milestone {index} has {} valid signature(s), but at least {} are required",
+                signers.len(),
+                self.min_threshold
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the public keys that are applicable at milestone `index`.
+    fn applicable_public_keys(&self, index: u32) -> anyhow::Result<HashSet<[u8; 32]>> {
+        self.key_ranges
+            .iter()
+            .filter(|range| {
+                range.start_index <= index && (range.end_index == 0 || index <= range.end_index)
+            })
+            .map(|range| {
+                prefix_hex::decode::<[u8; 32]>(&range.public_key).map_err(|err| {
+                    anyhow::anyhow!("invalid public key {}: {err:?}", range.public_key)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto::signatures::ed25519::SecretKey;
+
+    use super::{MilestoneCommittee, MilestoneKeyRange};
+
+    fn committee(keys: &[&SecretKey], min_threshold: usize) -> MilestoneCommittee {
+        MilestoneCommittee::new(
+            min_threshold,
+            keys.iter()
+                .map(|key| MilestoneKeyRange {
+                    public_key: prefix_hex::encode(key.public_key().to_bytes()),
+                    start_index: 10,
+                    end_index: 0,
+                })
+                .collect(),
+        )
+    }
+
+    fn sign(key: &SecretKey, message: &[u8; 32]) -> ([u8; 32], [u8; 64]) {
+        (key.public_key().to_bytes(), key.sign(message).to_bytes())
+    }
+
+    #[test]
+    fn test_milestone_committee_threshold() {
+        let key1 = SecretKey::from_bytes(rand::random());
+        let key2 = SecretKey::from_bytes(rand::random());
+        let key3 = SecretKey::from_bytes(rand::random());
+        let essence_hash: [u8; 32] = rand::random();
+
+        let committee = committee(&[&key1, &key2, &key3], 2);
+
+        let signatures = [sign(&key1, &essence_hash), sign(&key3, &essence_hash)];
+        assert!(committee
+            .verify_signatures(10, &essence_hash, signatures.into_iter())
+            .is_ok());
+
+        let signatures = [sign(&key1, &essence_hash), sign(&key1, &essence_hash)];
+        assert!(committee
+            .verify_signatures(10, &essence_hash, signatures.into_iter())
+            .is_err());
+
+        // Keys are not applicable before their start index.
+        let signatures = [sign(&key1, &essence_hash), sign(&key3, &essence_hash)];
+        assert!(committee
+            .verify_signatures(9, &essence_hash, signatures.into_iter())
+            .is_err());
+    }
+
+    #[test]
+    fn test_milestone_committee_rejects_invalid_signatures() {
+        let key1 = SecretKey::from_bytes(rand::random());
+        let key2 = SecretKey::from_bytes(rand::random());
+        let outsider = SecretKey::from_bytes(rand::random());
+        let essence_hash: [u8; 32] = rand::random();
+
+        let committee = committee(&[&key1, &key2], 1);
+
+        let signatures = [sign(&outsider, &essence_hash)];
+        assert!(committee
+            .verify_signatures(10, &essence_hash, signatures.into_iter())
+            .is_err());
+
+        let other_hash: [u8; 32] = rand::random();
+        let signatures = [sign(&key1, &other_hash)];
+        assert!(committee
+            .verify_signatures(10, &essence_hash, signatures.into_iter())
+            .is_err());
+    }
+}
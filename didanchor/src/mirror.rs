@@ -0,0 +1,383 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use crypto::hashes::{blake2b::Blake2b256, Digest, Output};
+use did_common::VerifiableChainOfCustody;
+use futures::StreamExt;
+use identity_core::convert::FromJson;
+use identity_iota_core::did::IotaDID;
+use iota_client::block::output::AliasId;
+use ipfs_cluster::IpfsCluster;
+use merkle_tree::DigestExt;
+use packable::{unpacker::SliceUnpacker, Packable};
+use tokio::{
+    fs::File,
+    io::AsyncWriteExt,
+    sync::{broadcast, oneshot},
+};
+
+use crate::{
+    audit::check_root,
+    background_task::{BackgroundTask, EventSender},
+    fs_sync::sync_parent_dir,
+    AliasContent, ContentStore, DIDIndex, LedgerAnchor, RootCheck,
+};
+
+/// The outcome of mirroring a new index of an alias.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct MirrorReport {
+    pub alias_id: AliasId,
+    pub state_index: u32,
+    pub index_cid: String,
+    /// The CIDs that were newly pinned, including the index.
+    pub pinned: Vec<String>,
+    /// The chains of custody that failed verification and were not pinned.
+    pub rejected: Vec<RejectedChain>,
+    /// The CIDs of superseded content that were unpinned.
+    pub unpinned: Vec<String>,
+}
+
+/// A chain of custody referenced by an index that was not mirrored.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RejectedChain {
+    pub did: IotaDID,
+    pub cid: String,
+    pub reason: String,
+}
+
+/// The content of an alias that a [`Mirror`] keeps pinned.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct MirroredState {
+    /// The last state that was mirrored completely, if any.
+    state_index: Option<u32>,
+    index_cid: Option<String>,
+    pinned: HashSet<String>,
+}
+
+/// Mirrors the DIDs of other publishers on an own IPFS cluster, so that they can be resolved
+/// from a cluster one trusts and remain available if the publisher's nodes go offline.
+///
+/// Every new index of an alias is fetched from the `source` and pinned on the cluster together with
+/// the chains of custody it references, but only those that are committed to by the merkle root of the
+/// alias content. Content that is no longer referenced by the current index is unpinned.
+///
+/// Which content was pinned is only kept in memory, unless a path is given with [`Mirror::with_state_path`].
+/// Otherwise, a restarted mirror pins the current index again, but doesn't unpin content that was
+/// superseded before the restart.
+pub struct Mirror {
+    ledger: Arc<dyn LedgerAnchor>,
+    source: Arc<dyn ContentStore>,
+    cluster: IpfsCluster,
+    mirrored: HashMap<AliasId, MirroredState>,
+    // Where the mirrored state is persisted, if anywhere.
+    state_path: Option<PathBuf>,
+}
+
+impl Mirror {
+    /// The maximum number of chains of custody that are fetched and verified at once.
+    pub const VERIFY_CONCURRENCY: usize = 16;
+
+    /// Creates a mirror that reads the alias content from the `ledger`, fetches the indices and chains
+    /// of custody from the `source`, e.g. a [`KuboStore`](crate::KuboStore), and pins them on the `cluster`.
+    pub fn new(
+        ledger: impl LedgerAnchor + 'static,
+        source: impl ContentStore + 'static,
+        cluster: IpfsCluster,
+    ) -> Self {
+        Self {
+            ledger: Arc::new(ledger),
+            source: Arc::new(source),
+            cluster,
+            mirrored: HashMap::new(),
+            state_path: None,
+        }
+    }
+
+    /// Persists which content was pinned at `path` after every sync, and loads what a previous run
+    /// persisted there, so that content superseded while the mirror was stopped is still unpinned.
+    pub async fn with_state_path(mut self, path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path: PathBuf = path.as_ref().to_owned();
+
+        let mirrored: Vec<(AliasId, MirroredState)> = match tokio::fs::read(&path).await {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(anyhow::anyhow!(err)),
+        };
+
+        self.mirrored = mirrored.into_iter().collect();
+        self.state_path = Some(path);
+
+        Ok(self)
+    }
+
+    /// Mirrors the current index of the alias.
+    ///
+    /// Returns `None` if the alias doesn't exist or its index was already mirrored.
+    pub async fn sync(&mut self, alias_id: AliasId) -> anyhow::Result<Option<MirrorReport>> {
+        let state_index: u32 = match self.ledger.state_index(alias_id).await? {
+            Some(state_index) => state_index,
+            None => return Ok(None),
+        };
+
+        if let Some(mirrored) = self.mirrored.get(&alias_id) {
+            if mirrored.state_index == Some(state_index) {
+                return Ok(None);
+            }
+        }
+
+        let content: AliasContent = self
+            .ledger
+            .read_content(alias_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no content found for alias id {alias_id}"))?;
+
+        // Only the state index moved on, e.g. because a commit republished an unchanged index.
+        if let Some(mirrored) = self.mirrored.get_mut(&alias_id) {
+            if mirrored.index_cid.as_ref() == Some(&content.index_cid) {
+                mirrored.state_index = Some(state_index);
+                self.save().await?;
+                return Ok(None);
+            }
+        }
+
+        let index: DIDIndex =
+            DIDIndex::from_json_slice(&self.source.get(&content.index_cid).await?)?;
+
+        let mut referenced: Vec<String> = Vec::with_capacity(index.len() + 1);
+        let mut rejected: Vec<RejectedChain> = Vec::new();
+
+        let source: &dyn ContentStore = self.source.as_ref();
+        let alias_content: &AliasContent = &content;
+        let verified: Vec<(&IotaDID, &String, anyhow::Result<VerifiedLeaf>)> =
+            futures::stream::iter(index.iter())
+                .map(|(did, cid)| async move {
+                    let result = Self::verify_chain(source, alias_content, did, cid).await;
+                    (did, cid, result)
+                })
+                .buffered(Self::VERIFY_CONCURRENCY)
+                .collect()
+                .await;
+
+        // If the leaves of all chains reproduce the root, it commits to every one of them. Otherwise,
+        // only the stored proof can vouch for a chain, although it might be against an earlier root.
+        let leaves: Option<Vec<(usize, IotaDID, Output<Blake2b256>)>> = verified
+            .iter()
+            .map(|(did, _, result)| {
+                let leaf: &VerifiedLeaf = result.as_ref().ok()?;
+                Some((leaf.index, (*did).clone(), leaf.hash))
+            })
+            .collect();
+        let reproduced: bool = match leaves {
+            Some(leaves) => check_root(leaves, &content.merkle_root) == RootCheck::Reproduced,
+            None => false,
+        };
+
+        for (did, cid, result) in verified {
+            let result: anyhow::Result<()> = result.and_then(|leaf| {
+                if reproduced || leaf.proof_verified {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!("invalid merkle proof for {did}"))
+                }
+            });
+
+            match result {
+                Ok(()) => referenced.push(cid.clone()),
+                Err(err) => {
+                    log::warn!("not mirroring the chain of custody of {did}: {err:#}");
+
+                    rejected.push(RejectedChain {
+                        did: did.clone(),
+                        cid: cid.clone(),
+                        reason: format!("{err:#}"),
+                    });
+                }
+            }
+        }
+
+        // The index is pinned last, so that the chains it references are available before it is.
+        referenced.push(content.index_cid.clone());
+        rejected.sort_by_key(|rejected| rejected.did.to_string());
+
+        let mut mirrored: MirroredState = self.mirrored.remove(&alias_id).unwrap_or_default();
+        let mut pinned: Vec<String> = Vec::new();
+
+        for cid in referenced.iter() {
+            if mirrored.pinned.contains(cid) {
+                continue;
+            }
+
+            if let Err(err) = self.cluster.pin(cid).await {
+                // Keep what was pinned so far, so that it is unpinned once superseded.
+                self.mirrored.insert(alias_id, mirrored);
+
+                if let Err(save_err) = self.save().await {
+                    log::warn!("failed to persist the mirrored state: {save_err:#}");
+                }
+
+                return Err(err);
+            }
+
+            mirrored.pinned.insert(cid.clone());
+            pinned.push(cid.clone());
+        }
+
+        let superseded: Vec<String> = mirrored
+            .pinned
+            .iter()
+            .filter(|cid| !referenced.contains(cid))
+            .cloned()
+            .collect();
+        let mut unpinned: Vec<String> = Vec::new();
+
+        for cid in superseded {
+            mirrored.pinned.remove(&cid);
+
+            // Other aliases might reference the same content.
+            if self
+                .mirrored
+                .values()
+                .any(|other| other.pinned.contains(&cid))
+            {
+                continue;
+            }
+
+            match self.cluster.unpin(&cid).await {
+                Ok(()) => unpinned.push(cid),
+                Err(err) => log::warn!("failed to unpin superseded content {cid}: {err:#}"),
+            }
+        }
+
+        mirrored.state_index = Some(state_index);
+        mirrored.index_cid = Some(content.index_cid.clone());
+        self.mirrored.insert(alias_id, mirrored);
+        self.save().await?;
+
+        Ok(Some(MirrorReport {
+            alias_id,
+            state_index,
+            index_cid: content.index_cid,
+            pinned,
+            rejected,
+            unpinned,
+        }))
+    }
+
+    /// Fetches the chain of custody of `did`, verifies that it is valid and returns its leaf.
+    async fn verify_chain(
+        source: &dyn ContentStore,
+        content: &AliasContent,
+        did: &IotaDID,
+        cid: &str,
+    ) -> anyhow::Result<VerifiedLeaf> {
+        let bytes: Bytes = source.get(cid).await?;
+
+        let mut unpacker = SliceUnpacker::new(bytes.as_ref());
+        let coc: VerifiableChainOfCustody =
+            VerifiableChainOfCustody::unpack::<_, true>(&mut unpacker)
+                .map_err(|err| anyhow::anyhow!("invalid chain of custody: {err:?}"))?;
+
+        match coc.chain_of_custody.0.first() {
+            Some(first) if first.document.id() == did => (),
+            _ => anyhow::bail!("the chain of custody does not belong to {did}"),
+        }
+
+        let serialized = coc.chain_of_custody.serialize_to_vec()?;
+        let hash: Output<Blake2b256> = Blake2b256::new().hash_leaf(&serialized);
+
+        let leaf = VerifiedLeaf {
+            index: coc.proof.index(),
+            hash,
+            proof_verified: coc.proof.verify_hash(&content.merkle_root, hash),
+        };
+
+        coc.chain_of_custody.into_document()?;
+
+        Ok(leaf)
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let path: &PathBuf = match self.state_path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let mirrored: Vec<(&AliasId, &MirroredState)> = self.mirrored.iter().collect();
+        let tmp_path: PathBuf = path.with_extension("tmp");
+
+        let mut tmp_file: File = File::create(&tmp_path).await?;
+        tmp_file
+            .write_all(&serde_json::to_vec_pretty(&mirrored)?)
+            .await?;
+        tmp_file.sync_all().await?;
+
+        tokio::fs::rename(&tmp_path, path).await?;
+        sync_parent_dir(path).await
+    }
+
+    /// Spawns a task on the current runtime that mirrors the aliases every `poll_interval`.
+    pub fn spawn(self, alias_ids: Vec<AliasId>, poll_interval: Duration) -> MirrorService {
+        let task = BackgroundTask::spawn(MirrorService::EVENT_CAPACITY, |events, shutdown| {
+            MirrorService::run(self, alias_ids, poll_interval, events, shutdown)
+        });
+
+        MirrorService { task }
+    }
+}
+
+/// The leaf of a valid chain of custody.
+struct VerifiedLeaf {
+    index: usize,
+    hash: Output<Blake2b256>,
+    /// Whether the stored proof verifies against the merkle root.
+    proof_verified: bool,
+}
+
+/// A [`Mirror`] running in the background.
+pub struct MirrorService {
+    task: BackgroundTask<MirrorReport, ()>,
+}
+
+impl MirrorService {
+    const EVENT_CAPACITY: usize = 64;
+
+    /// Returns a receiver that is sent the report of every index mirrored from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<MirrorReport> {
+        self.task.subscribe()
+    }
+
+    /// Stops mirroring the aliases. The mirrored content stays pinned.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.task.shutdown().await
+    }
+
+    async fn run(
+        mut mirror: Mirror,
+        alias_ids: Vec<AliasId>,
+        poll_interval: Duration,
+        events: EventSender<MirrorReport>,
+        mut shutdown: oneshot::Receiver<()>,
+    ) {
+        let mut ticker = tokio::time::interval(poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    for alias_id in alias_ids.iter() {
+                        match mirror.sync(*alias_id).await {
+                            Ok(Some(report)) => events.send(report),
+                            Ok(None) => (),
+                            Err(err) => log::warn!("failed to mirror alias {alias_id}: {err:#}"),
+                        }
+                    }
+                }
+                _ = &mut shutdown => break,
+            }
+        }
+    }
+}
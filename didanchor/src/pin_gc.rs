@@ -0,0 +1,259 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{fs_sync::sync_parent_dir, ChainStorage};
+
+/// Determines how long superseded content stays pinned.
+///
+/// Resolvers that still hold a previous state of the alias need its content to be available,
+/// so it should not be unpinned right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Unpin content once it was superseded for the given number of seconds.
+    Duration { secs: u64 },
+    /// Unpin content once the given number of alias states were published after the one that superseded it.
+    States { count: u64 },
+    /// Never unpin content, e.g. to keep the full history available for audits.
+    Forever,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy::Duration { secs: 24 * 60 * 60 }
+    }
+}
+
+/// Content that is no longer referenced by the latest alias state.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SupersededPin {
+    pub cid: String,
+    pub superseded_at: SystemTime,
+    /// The alias state that superseded the content, counted by the collector.
+    pub superseded_in_state: u64,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct PinRecords {
+    state: u64,
+    superseded: Vec<SupersededPin>,
+}
+
+/// Unpins superseded content once the [`RetentionPolicy`] allows it.
+///
+/// Superseded content is persisted, so that it is eventually unpinned even if the anchor restarts in between.
+pub struct PinGarbageCollector {
+    path: PathBuf,
+    policy: RetentionPolicy,
+    records: PinRecords,
+}
+
+impl PinGarbageCollector {
+    /// Loads the superseded content recorded at `path`.
+    pub async fn load(path: impl AsRef<Path>, policy: RetentionPolicy) -> anyhow::Result<Self> {
+        let path: PathBuf = path.as_ref().to_owned();
+
+        let records: PinRecords = match tokio::fs::read(&path).await {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => PinRecords::default(),
+            Err(err) => return Err(anyhow::anyhow!(err)),
+        };
+
+        Ok(Self {
+            path,
+            policy,
+            records,
+        })
+    }
+
+    /// Returns the content that is superseded but still pinned.
+    pub fn superseded(&self) -> &[SupersededPin] {
+        &self.records.superseded
+    }
+
+    /// Records the content that was `superseded` by a new alias state, which `references` the given content.
+    ///
+    /// Recording the same state twice, e.g. when an interrupted commit is resumed, has no effect.
+    pub async fn record(
+        &mut self,
+        superseded: Vec<String>,
+        references: &[String],
+    ) -> anyhow::Result<()> {
+        self.record_at(superseded, references, SystemTime::now());
+        self.save().await
+    }
+
+    /// Unpins all content whose retention period has passed and returns how much was unpinned.
+    pub async fn collect(&mut self, storage: &ChainStorage) -> anyhow::Result<usize> {
+        let collectable: Vec<String> = self.collectable(SystemTime::now());
+        let mut unpinned: usize = 0;
+
+        for cid in collectable {
+            match storage.unpin(&cid).await {
+                Ok(()) => {
+                    self.records.superseded.retain(|pin| pin.cid != cid);
+                    unpinned += 1;
+                }
+                Err(err) => log::warn!("failed to unpin superseded content {cid}: {err:#}"),
+            }
+        }
+
+        if unpinned > 0 {
+            log::debug!("unpinned {unpinned} superseded content(s)");
+            self.save().await?;
+        }
+
+        Ok(unpinned)
+    }
+
+    fn record_at(&mut self, superseded: Vec<String>, references: &[String], now: SystemTime) {
+        let references: HashSet<&str> = references.iter().map(String::as_str).collect();
+
+        // Content that is referenced again must not be unpinned.
+        self.records
+            .superseded
+            .retain(|pin| !references.contains(pin.cid.as_str()));
+
+        if self.policy == RetentionPolicy::Forever {
+            return;
+        }
+
+        let recorded: HashSet<&str> = self
+            .records
+            .superseded
+            .iter()
+            .map(|pin| pin.cid.as_str())
+            .collect();
+        let new: Vec<String> = superseded
+            .into_iter()
+            .filter(|cid| !recorded.contains(cid.as_str()))
+            .collect();
+
+        if new.is_empty() {
+            return;
+        }
+
+        self.records.state += 1;
+
+        for cid in new {
+            self.records.superseded.push(SupersededPin {
+                cid,
+                superseded_at: now,
+                superseded_in_state: self.records.state,
+            });
+        }
+    }
+
+    fn collectable(&self, now: SystemTime) -> Vec<String> {
+        self.records
+            .superseded
+            .iter()
+            .filter(|pin| match self.policy {
+                RetentionPolicy::Duration { secs } => now
+                    .duration_since(pin.superseded_at)
+                    .map(|age| age >= Duration::from_secs(secs))
+                    .unwrap_or(false),
+                RetentionPolicy::States { count } => {
+                    self.records.state - pin.superseded_in_state >= count
+                }
+                RetentionPolicy::Forever => false,
+            })
+            .map(|pin| pin.cid.clone())
+            .collect()
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let tmp_path: PathBuf = self.path.with_extension("tmp");
+
+        let mut tmp_file: File = File::create(&tmp_path).await?;
+        tmp_file
+            .write_all(&serde_json::to_vec_pretty(&self.records)?)
+            .await?;
+        tmp_file.sync_all().await?;
+
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        sync_parent_dir(&self.path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{PinGarbageCollector, PinRecords, RetentionPolicy};
+
+    fn collector(policy: RetentionPolicy) -> PinGarbageCollector {
+        PinGarbageCollector {
+            path: PathBuf::new(),
+            policy,
+            records: PinRecords::default(),
+        }
+    }
+
+    fn cids(cids: &[&str]) -> Vec<String> {
+        cids.iter().map(|cid| cid.to_string()).collect()
+    }
+
+    #[test]
+    fn test_pin_gc_retention_duration() {
+        let mut gc = collector(RetentionPolicy::Duration { secs: 60 });
+        let now = SystemTime::now();
+
+        gc.record_at(cids(&["a", "b"]), &[], now);
+
+        assert!(gc.collectable(now + Duration::from_secs(59)).is_empty());
+        assert_eq!(
+            gc.collectable(now + Duration::from_secs(60)),
+            cids(&["a", "b"])
+        );
+    }
+
+    #[test]
+    fn test_pin_gc_retention_states() {
+        let mut gc = collector(RetentionPolicy::States { count: 2 });
+        let now = SystemTime::now();
+
+        gc.record_at(cids(&["a"]), &[], now);
+        gc.record_at(cids(&["b"]), &[], now);
+        assert!(gc.collectable(now).is_empty());
+
+        gc.record_at(cids(&["c"]), &[], now);
+        assert_eq!(gc.collectable(now), cids(&["a"]));
+    }
+
+    #[test]
+    fn test_pin_gc_retention_forever() {
+        let mut gc = collector(RetentionPolicy::Forever);
+        let now = SystemTime::now();
+
+        gc.record_at(cids(&["a"]), &[], now);
+
+        assert!(gc.superseded().is_empty());
+        assert!(gc
+            .collectable(now + Duration::from_secs(u32::MAX as u64))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_pin_gc_record_is_idempotent_and_respects_references() {
+        let mut gc = collector(RetentionPolicy::States { count: 1 });
+        let now = SystemTime::now();
+
+        gc.record_at(cids(&["a", "b"]), &[], now);
+        gc.record_at(cids(&["a", "b"]), &[], now);
+        assert_eq!(gc.superseded().len(), 2);
+        assert!(gc.collectable(now).is_empty());
+
+        // "b" is referenced by the new state, so it must stay pinned.
+        gc.record_at(cids(&["c"]), &cids(&["b"]), now);
+        assert_eq!(gc.collectable(now), cids(&["a"]));
+    }
+}
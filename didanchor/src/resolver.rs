@@ -1,6 +1,13 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use anyhow::Context;
 use bytes::Bytes;
 use did_common::VerifiableChainOfCustody;
+use futures::StreamExt;
 use identity_core::convert::FromJson;
 use identity_did::{
     did::{CoreDID, DID},
@@ -9,90 +16,370 @@ use identity_did::{
 use identity_iota_core::did::IotaDID;
 use iota_client::{
     api_types::responses::OutputResponse,
-    block::output::{AliasId, AliasOutput, Output, OutputId},
+    block::{
+        output::{AliasId, AliasOutput, Output, OutputId},
+        BlockId,
+    },
     Client as IotaClient,
 };
 use ipfs_client::IpfsClient;
 use multiaddr::Multiaddr;
 use packable::{unpacker::SliceUnpacker, Packable};
+use url::Url;
 
-use crate::{AliasContent, DIDIndex};
+use crate::{
+    evidence::check_evidence, AliasContent, AnchorOutput, ContentStore, DIDIndex, EvidenceBundle,
+    IpfsNodePublicAddress, KuboStore, LedgerAnchor, LedgerInclusionProof, MilestoneCommittee,
+};
 
 pub struct Resolver {
     iota_client: IotaClient,
+    iota_endpoint: Url,
+    http_client: reqwest::Client,
     ipfs_client: IpfsClient,
+    ledger: Arc<dyn LedgerAnchor>,
+    content_store: Arc<dyn ContentStore>,
+    committee: Option<MilestoneCommittee>,
+    concurrency_limit: usize,
+    peering_timeout: Duration,
+    peering_ttl: Duration,
+    // The cluster nodes that were recently peered with and when that happened.
+    peered_nodes: Mutex<HashMap<Multiaddr, Instant>>,
 }
 
 impl Resolver {
+    /// The default number of chains of custody that are fetched concurrently by [`Resolver::resolve_many`].
+    pub const DEFAULT_CONCURRENCY_LIMIT: usize = 16;
+    /// The default time after which connecting to a single cluster node is given up.
+    pub const DEFAULT_PEERING_TIMEOUT: Duration = Duration::from_secs(5);
+    /// The default time for which a successfully peered cluster node is not connected to again.
+    pub const DEFAULT_PEERING_TTL: Duration = Duration::from_secs(300);
+
     pub fn new(iota_endpoint: &str, ipfs_endpoint: &str) -> anyhow::Result<Self> {
         let client: IotaClient = IotaClient::builder()
             .with_primary_node(iota_endpoint, None)?
             .finish()?;
 
-        let ipfs_client = IpfsClient::new(vec![ipfs_endpoint
+        let ipfs_endpoint: Url = ipfs_endpoint
             .parse()
-            .context("unable to parse IPFS endpoint into URL")?])?;
+            .context("unable to parse IPFS endpoint into URL")?;
+        let ipfs_client = IpfsClient::new(vec![ipfs_endpoint.clone()])?;
+
+        let iota_endpoint: Url = iota_endpoint
+            .parse()
+            .context("unable to parse IOTA endpoint into URL")?;
 
         Ok(Self {
+            ledger: Arc::new(AnchorOutput::read_only(client.clone())),
+            content_store: Arc::new(KuboStore::new(ipfs_endpoint)?),
             iota_client: client,
+            iota_endpoint,
+            http_client: reqwest::Client::new(),
             ipfs_client,
+            committee: None,
+            concurrency_limit: Self::DEFAULT_CONCURRENCY_LIMIT,
+            peering_timeout: Self::DEFAULT_PEERING_TIMEOUT,
+            peering_ttl: Self::DEFAULT_PEERING_TTL,
+            peered_nodes: Mutex::new(HashMap::new()),
         })
     }
 
+    /// Enables light-client mode, in which the Alias Output returned by the IOTA node is not trusted.
+    ///
+    /// Instead, the block that created the output is requested together with a proof of its inclusion
+    /// in a milestone. The output is only used if the inclusion proof is valid and the milestone is
+    /// signed by the given `committee`. This requires the node to run the proof-of-inclusion plugin.
+    ///
+    /// Note that this proves that the output was created by the alias' controller, but not that it is
+    /// still unspent, i.e. a malicious node can still serve a previous state of the alias.
+    pub fn with_milestone_committee(mut self, committee: MilestoneCommittee) -> Self {
+        self.committee = Some(committee);
+        self
+    }
+
+    /// Reads the alias content from the given `ledger` instead of the IOTA node, e.g. to test
+    /// the resolver without a network.
+    ///
+    /// The ledger is not used in light-client mode or to export evidence,
+    /// both of which require the ledger inclusion proofs of the IOTA node.
+    pub fn with_ledger(mut self, ledger: impl LedgerAnchor + 'static) -> Self {
+        self.ledger = Arc::new(ledger);
+        self
+    }
+
+    /// Reads indices and chains of custody from the given `content_store` instead of the IPFS node.
+    pub fn with_content_store(mut self, content_store: impl ContentStore + 'static) -> Self {
+        self.content_store = Arc::new(content_store);
+        self
+    }
+
+    /// Sets the maximum number of chains of custody that are fetched concurrently
+    /// by [`Resolver::resolve_many`].
+    pub fn with_concurrency_limit(mut self, concurrency_limit: usize) -> Self {
+        self.concurrency_limit = concurrency_limit.max(1);
+        self
+    }
+
+    /// Sets the time after which connecting to a single cluster node is given up.
+    pub fn with_peering_timeout(mut self, peering_timeout: Duration) -> Self {
+        self.peering_timeout = peering_timeout;
+        self
+    }
+
+    /// Sets the time for which a successfully peered cluster node is not connected to again.
+    pub fn with_peering_ttl(mut self, peering_ttl: Duration) -> Self {
+        self.peering_ttl = peering_ttl;
+        self
+    }
+
     /// Resolve the given DID into its corresponding DID document.
     ///
     /// Ensures validity in the chain of custody, as well as ensuring it is the version of the CoC
     /// committed to by the anchoring node.
     pub async fn resolve(&self, did: &CoreDID) -> anyhow::Result<Option<CoreDocument>> {
-        let mut split = did.method_id().split(':');
-        let alias_id: AliasId = AliasId::new(prefix_hex::decode(split.next().unwrap()).unwrap());
-        let did_tag = split.next().unwrap();
-        let did = IotaDID::parse(format!("did:iota:{did_tag}")).unwrap();
+        let (alias_id, did): (AliasId, IotaDID) = parse_did(did)?;
 
-        let alias_content: AliasContent = resolve_alias_content(&self.iota_client, alias_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("no output found for alias id {alias_id}"))?;
+        let (content, index): (AliasContent, DIDIndex) = self.resolve_index(alias_id).await?;
 
-        self.resolve_did(&alias_content, &did).await
+        self.resolve_did(&content, &index, &did).await
     }
 
-    async fn resolve_did(
+    /// Export an [`EvidenceBundle`] for the given DID, which proves the current state of its
+    /// DID document and can be verified offline with [`verify_evidence`](crate::verify_evidence).
+    ///
+    /// Returns `None` if the DID is not contained in the index of its publisher.
+    pub async fn export_evidence(&self, did: &CoreDID) -> anyhow::Result<Option<EvidenceBundle>> {
+        let (alias_id, iota_did): (AliasId, IotaDID) = parse_did(did)?;
+
+        let (output_id, output_response): (OutputId, OutputResponse) =
+            resolve_alias_output_response(&self.iota_client, alias_id)
+                .await?
+                .ok_or(ResolutionError::AliasNotFound(alias_id))?;
+
+        let alias_content: AliasContent = match Output::try_from(&output_response.output)? {
+            Output::Alias(alias_output) => {
+                AliasContent::from_state_metadata(alias_output.state_metadata())?.0
+            }
+            _ => anyhow::bail!("output {output_id} is not an alias output"),
+        };
+
+        let index: DIDIndex = self.resolve_index_of(&alias_content).await?;
+
+        let cid: &str = if let Some(cid) = index.get(&iota_did) {
+            cid
+        } else {
+            return Ok(None);
+        };
+
+        let chain_of_custody: Bytes = self.content_store.get(cid).await?;
+
+        let block_id: BlockId = output_response.metadata.block_id.parse()?;
+        let inclusion_proof: LedgerInclusionProof =
+            LedgerInclusionProof::fetch(&self.http_client, &self.iota_endpoint, &block_id).await?;
+
+        let evidence = EvidenceBundle {
+            did: did.to_owned(),
+            output_id: output_id.to_string(),
+            alias_output: output_response.output,
+            inclusion_proof,
+            alias_content,
+            chain_of_custody: chain_of_custody.to_vec(),
+        };
+
+        // Make sure we never hand out evidence that does not hold up.
+        check_evidence(&evidence, self.committee.as_ref())?;
+
+        Ok(Some(evidence))
+    }
+
+    /// Resolve all of the given DIDs into their corresponding DID documents.
+    ///
+    /// The DIDs are grouped by their alias id, so that the Alias Output and the index of each publisher
+    /// are only fetched once. The chains of custody are then fetched concurrently, bounded by the
+    /// configured concurrency limit.
+    ///
+    /// Returns one result per DID in the same order as `dids`.
+    pub async fn resolve_many(
         &self,
-        content: &AliasContent,
-        did: &IotaDID,
-    ) -> anyhow::Result<Option<CoreDocument>> {
-        // Peer the local node with a cluster node to speed up the subsequent lookup.
-        let node_multiaddrs = content.ipfs_node_addrs.iter().map(|addr| {
-            let mut multiaddr = Multiaddr::empty();
-            multiaddr.push(addr.host.clone());
+        dids: &[CoreDID],
+    ) -> Vec<anyhow::Result<Option<CoreDocument>>> {
+        let mut results: Vec<Option<anyhow::Result<Option<CoreDocument>>>> =
+            dids.iter().map(|_| None).collect();
+
+        let mut groups: HashMap<AliasId, Vec<(usize, IotaDID)>> = HashMap::new();
+
+        for (position, did) in dids.iter().enumerate() {
+            match parse_did(did) {
+                Ok((alias_id, did)) => groups.entry(alias_id).or_default().push((position, did)),
+                Err(err) => results[position] = Some(Err(err)),
+            }
+        }
+
+        for (alias_id, group) in groups {
+            let (content, index): (AliasContent, DIDIndex) =
+                match self.resolve_index(alias_id).await {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        for (position, _) in group {
+                            results[position] = Some(Err(share_error(&err)));
+                        }
+                        continue;
+                    }
+                };
+
+            let resolved: Vec<(usize, anyhow::Result<Option<CoreDocument>>)> =
+                futures::stream::iter(group.into_iter().map(|(position, did)| {
+                    let content: &AliasContent = &content;
+                    let index: &DIDIndex = &index;
 
-            for protocol in addr.swarm_port.iter() {
-                multiaddr.push(protocol);
+                    async move { (position, self.resolve_did(content, index, &did).await) }
+                }))
+                .buffer_unordered(self.concurrency_limit)
+                .collect()
+                .await;
+
+            for (position, result) in resolved {
+                results[position] = Some(result);
             }
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every DID should have been assigned a result"))
+            .collect()
+    }
+
+    /// Resolves the Alias Output of the given `alias_id` and the index it points to.
+    async fn resolve_index(&self, alias_id: AliasId) -> anyhow::Result<(AliasContent, DIDIndex)> {
+        let content: AliasContent = self
+            .resolve_content(alias_id)
+            .await?
+            .ok_or(ResolutionError::AliasNotFound(alias_id))?;
+
+        let index: DIDIndex = self.resolve_index_of(&content).await?;
+
+        Ok((content, index))
+    }
+
+    /// Resolves the content of the Alias Output of `alias_id`, verifying its inclusion in the ledger
+    /// if light-client mode is enabled.
+    async fn resolve_content(&self, alias_id: AliasId) -> anyhow::Result<Option<AliasContent>> {
+        let committee: &MilestoneCommittee = match self.committee {
+            Some(ref committee) => committee,
+            None => return self.ledger.read_content(alias_id).await,
+        };
+
+        let (output_id, output_response): (OutputId, OutputResponse) =
+            match resolve_alias_output_response(&self.iota_client, alias_id).await? {
+                Some(output) => output,
+                None => return Ok(None),
+            };
 
-            multiaddr.push(addr.peer_id.clone());
-            multiaddr
-        });
+        let block_id: BlockId = output_response.metadata.block_id.parse()?;
+        let inclusion_proof: LedgerInclusionProof =
+            LedgerInclusionProof::fetch(&self.http_client, &self.iota_endpoint, &block_id).await?;
 
-        // TODO: Poll all swarm connect futures at once, or consider peering with just one random node.
-        for addr in node_multiaddrs {
-            self.ipfs_client.swarm_connect(addr.to_string()).await?;
+        let milestone_index = inclusion_proof
+            .verify_with_committee(committee)
+            .with_context(|| format!("unable to verify the inclusion of output {output_id}"))?;
+
+        // Only use the output from the verified block, not the one returned by the node.
+        let alias_output: AliasOutput = inclusion_proof.alias_output(&output_id, &alias_id)?;
+
+        log::debug!("verified inclusion of output {output_id} in milestone {milestone_index}");
+
+        let (content, _) = AliasContent::from_state_metadata(alias_output.state_metadata())?;
+
+        Ok(Some(content))
+    }
+
+    /// Resolves the index that `content` points to.
+    async fn resolve_index_of(&self, content: &AliasContent) -> anyhow::Result<DIDIndex> {
+        self.peer_with_nodes(content).await;
+
+        let index_bytes: Bytes = self.content_store.get(&content.index_cid).await?;
+
+        Ok(DIDIndex::from_json_slice(&index_bytes)?)
+    }
+
+    /// Peers the local node with the cluster nodes in `content` to speed up the subsequent lookups.
+    ///
+    /// All nodes are connected to concurrently and each attempt is bounded by the peering timeout.
+    /// Failures are only logged, since the content may still be reachable through the IPFS network.
+    async fn peer_with_nodes(&self, content: &AliasContent) {
+        let node_multiaddrs: Vec<Multiaddr> = content
+            .ipfs_node_addrs
+            .iter()
+            .map(IpfsNodePublicAddress::to_swarm_multiaddr)
+            .filter(|addr| !self.is_recently_peered(addr))
+            .collect();
+
+        if node_multiaddrs.is_empty() {
+            return;
         }
 
-        let index_bytes: Bytes = self.ipfs_client.cat(&content.index_cid).await?;
-        let index: DIDIndex = DIDIndex::from_json_slice(&index_bytes)?;
+        let peered: Vec<Multiaddr> = futures::stream::iter(node_multiaddrs)
+            .map(|addr| async move {
+                let connect = self.ipfs_client.swarm_connect(addr.to_string());
+
+                match tokio::time::timeout(self.peering_timeout, connect).await {
+                    Ok(Ok(())) => Some(addr),
+                    Ok(Err(err)) => {
+                        log::warn!("unable to peer with {addr}: {err:#}");
+                        None
+                    }
+                    Err(_) => {
+                        log::warn!("peering with {addr} timed out");
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(self.concurrency_limit)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
 
+        log::debug!("peered with {} cluster node(s)", peered.len());
+
+        let now = Instant::now();
+        let mut peered_nodes = self
+            .peered_nodes
+            .lock()
+            .expect("the lock should not be poisoned");
+        peered_nodes.retain(|_, time| now.duration_since(*time) < self.peering_ttl);
+        peered_nodes.extend(peered.into_iter().map(|addr| (addr, now)));
+    }
+
+    fn is_recently_peered(&self, addr: &Multiaddr) -> bool {
+        self.peered_nodes
+            .lock()
+            .expect("the lock should not be poisoned")
+            .get(addr)
+            .map(|time| time.elapsed() < self.peering_ttl)
+            .unwrap_or(false)
+    }
+
+    async fn resolve_did(
+        &self,
+        content: &AliasContent,
+        index: &DIDIndex,
+        did: &IotaDID,
+    ) -> anyhow::Result<Option<CoreDocument>> {
         let cid: &str = if let Some(cid) = index.get(did) {
             cid
         } else {
             return Ok(None);
         };
 
-        let bytes: Bytes = self.ipfs_client.cat(cid).await?;
+        let bytes: Bytes = self.content_store.get(cid).await?;
 
         let mut unpacker = SliceUnpacker::new(bytes.as_ref());
         let coc: VerifiableChainOfCustody =
-            VerifiableChainOfCustody::unpack::<_, false>(&mut unpacker).expect("TODO");
+            VerifiableChainOfCustody::unpack::<_, false>(&mut unpacker).map_err(|err| {
+                ResolutionError::InvalidChainOfCustody(format!(
+                    "invalid chain of custody at {cid} for {did}: {err:?}"
+                ))
+            })?;
 
         let serialized = coc.chain_of_custody.serialize_to_vec()?;
         let document = coc.chain_of_custody.into_document()?;
@@ -107,18 +394,74 @@ impl Resolver {
     }
 }
 
-pub(crate) async fn resolve_alias_content(
-    client: &IotaClient,
-    alias_id: AliasId,
-) -> anyhow::Result<Option<AliasContent>> {
-    let (_, _, alias_output) = if let Some(output) = resolve_alias_output(client, alias_id).await? {
-        output
-    } else {
-        return Ok(None);
+/// Splits a `did:iota:<alias_id>:<did_tag>` into the alias id and the DID of the document.
+pub(crate) fn parse_did(did: &CoreDID) -> anyhow::Result<(AliasId, IotaDID)> {
+    let mut split = did.method_id().split(':');
+
+    let (alias_id, did_tag) = match (split.next(), split.next(), split.next()) {
+        (Some(alias_id), Some(did_tag), None) => (alias_id, did_tag),
+        _ => {
+            return Err(ResolutionError::InvalidDid(format!(
+                "expected a DID of the form `did:iota:<alias_id>:<tag>`, got {did}"
+            ))
+            .into())
+        }
     };
 
-    let content: AliasContent = AliasContent::from_json_slice(alias_output.state_metadata())?;
-    Ok(Some(content))
+    let alias_id: AliasId = AliasId::new(prefix_hex::decode(alias_id).map_err(|err| {
+        ResolutionError::InvalidDid(format!("invalid alias id in {did}: {err:?}"))
+    })?);
+    let did = IotaDID::parse(format!("did:iota:{did_tag}"))
+        .map_err(|err| ResolutionError::InvalidDid(format!("invalid tag in {did}: {err}")))?;
+
+    Ok((alias_id, did))
+}
+
+/// Resolution failures that callers may want to handle specifically,
+/// e.g. to map them to DID resolution error codes.
+///
+/// Returned wrapped in an [`anyhow::Error`] and can be obtained through [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone)]
+pub enum ResolutionError {
+    /// The DID is not of the form `did:iota:<alias_id>:<tag>`.
+    InvalidDid(String),
+    /// There is no Alias Output for the alias id of the DID.
+    AliasNotFound(AliasId),
+    /// The content stored for the DID is not a chain of custody.
+    InvalidChainOfCustody(String),
+}
+
+impl std::fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolutionError::InvalidDid(reason) => f.write_str(reason),
+            ResolutionError::InvalidChainOfCustody(reason) => f.write_str(reason),
+            ResolutionError::AliasNotFound(alias_id) => {
+                write!(f, "no output found for alias id {alias_id}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolutionError {}
+
+/// Copies an error that is shared by several results, keeping a [`ResolutionError`] in its chain,
+/// so that it can still be obtained with `downcast_ref`.
+fn share_error(err: &anyhow::Error) -> anyhow::Error {
+    let mut contexts: Vec<String> = Vec::new();
+
+    for cause in err.chain() {
+        if let Some(resolution_error) = cause.downcast_ref::<ResolutionError>() {
+            return contexts.into_iter().rev().fold(
+                anyhow::Error::new(resolution_error.clone()),
+                |err, context| err.context(context),
+            );
+        }
+
+        contexts.push(cause.to_string());
+    }
+
+    anyhow::anyhow!("{err:#}")
 }
 
 /// Resolve a did into an Alias Output and the associated identifiers.
@@ -126,13 +469,13 @@ pub(crate) async fn resolve_alias_output(
     client: &IotaClient,
     alias_id: AliasId,
 ) -> anyhow::Result<Option<(AliasId, OutputId, AliasOutput)>> {
-    let output_id: OutputId = match client.alias_output_id(alias_id).await {
-        Ok(output_id) => output_id,
-        Err(iota_client::Error::NotFound) => return Ok(None),
-        Err(err) => anyhow::bail!(err),
-    };
+    let (output_id, output_response) =
+        if let Some(output) = resolve_alias_output_response(client, alias_id).await? {
+            output
+        } else {
+            return Ok(None);
+        };
 
-    let output_response: OutputResponse = client.get_output(&output_id).await?;
     let output: Output = Output::try_from(&output_response.output)?;
 
     if let Output::Alias(alias_output) = output {
@@ -141,3 +484,19 @@ pub(crate) async fn resolve_alias_output(
         unreachable!("we requested an alias output. (TODO: turn into error later, though.)");
     }
 }
+
+/// Resolve the current Alias Output of `alias_id` together with its metadata.
+pub(crate) async fn resolve_alias_output_response(
+    client: &IotaClient,
+    alias_id: AliasId,
+) -> anyhow::Result<Option<(OutputId, OutputResponse)>> {
+    let output_id: OutputId = match client.alias_output_id(alias_id).await {
+        Ok(output_id) => output_id,
+        Err(iota_client::Error::NotFound) => return Ok(None),
+        Err(err) => anyhow::bail!(err),
+    };
+
+    let output_response: OutputResponse = client.get_output(&output_id).await?;
+
+    Ok(Some((output_id, output_response)))
+}
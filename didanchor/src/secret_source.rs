@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use iota_client::secret::{mnemonic::MnemonicSecretManager, SecretManager};
+
+/// Where a secret is read from, so that the config only refers to it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretRef {
+    /// The environment variable with the given name.
+    Env(String),
+    /// A file that must only be accessible by its owner.
+    File(PathBuf),
+}
+
+impl SecretRef {
+    /// Reads the secret without surrounding whitespace.
+    pub async fn read(&self) -> anyhow::Result<String> {
+        let secret: String = match self {
+            SecretRef::Env(name) => std::env::var(name)
+                .map_err(|err| anyhow::anyhow!("failed to read the secret from ${name}: {err}"))?,
+            SecretRef::File(path) => {
+                check_permissions(path).await?;
+
+                tokio::fs::read_to_string(path).await.map_err(|err| {
+                    anyhow::anyhow!("failed to read the secret from {}: {err}", path.display())
+                })?
+            }
+        };
+
+        let secret: &str = secret.trim();
+
+        if secret.is_empty() {
+            anyhow::bail!("the secret from {self} is empty");
+        }
+
+        Ok(secret.to_owned())
+    }
+}
+
+impl std::fmt::Display for SecretRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretRef::Env(name) => write!(f, "${name}"),
+            SecretRef::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Rejects secret files that can be read or written by anyone but their owner.
+#[cfg(unix)]
+async fn check_permissions(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode: u32 = tokio::fs::metadata(path).await?.permissions().mode();
+
+    if mode & 0o077 != 0 {
+        anyhow::bail!(
+            "{} must only be accessible by its owner, but has mode {:o} (try `chmod 600`)",
+            path.display(),
+            mode & 0o777
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn check_permissions(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// The secret that controls the Alias Output of an anchor.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecretSource {
+    /// A BIP-39 mnemonic.
+    Mnemonic { mnemonic: SecretRef },
+    /// A Stronghold snapshot, which requires the `stronghold` feature.
+    Stronghold {
+        snapshot_path: PathBuf,
+        password: SecretRef,
+    },
+    /// An [`ExternalSigner`](crate::ExternalSigner), e.g. backed by an HSM or KMS,
+    /// which is passed to [`Anchor::with_signer`](crate::Anchor::with_signer).
+    External,
+}
+
+impl SecretSource {
+    /// The environment variable the mnemonic is read from by default.
+    pub const DEFAULT_MNEMONIC_ENV: &'static str = "DIDANCHOR_MNEMONIC";
+
+    /// Creates the secret manager that signs with the secret.
+    pub async fn secret_manager(&self) -> anyhow::Result<SecretManager> {
+        match self {
+            SecretSource::Mnemonic { mnemonic } => Ok(SecretManager::Mnemonic(
+                MnemonicSecretManager::try_from_mnemonic(&mnemonic.read().await?)?,
+            )),
+            SecretSource::Stronghold {
+                snapshot_path,
+                password,
+            } => stronghold_secret_manager(snapshot_path, password).await,
+            SecretSource::External => {
+                anyhow::bail!("an external signer must be passed to the anchor")
+            }
+        }
+    }
+}
+
+impl Default for SecretSource {
+    fn default() -> Self {
+        SecretSource::Mnemonic {
+            mnemonic: SecretRef::Env(Self::DEFAULT_MNEMONIC_ENV.to_owned()),
+        }
+    }
+}
+
+#[cfg(feature = "stronghold")]
+async fn stronghold_secret_manager(
+    snapshot_path: &Path,
+    password: &SecretRef,
+) -> anyhow::Result<SecretManager> {
+    use iota_client::secret::stronghold::StrongholdSecretManager;
+
+    if !snapshot_path.exists() {
+        anyhow::bail!("no Stronghold snapshot at {}", snapshot_path.display());
+    }
+
+    Ok(SecretManager::Stronghold(
+        StrongholdSecretManager::builder()
+            .password(&password.read().await?)
+            .build(snapshot_path)?,
+    ))
+}
+
+#[cfg(not(feature = "stronghold"))]
+async fn stronghold_secret_manager(
+    _snapshot_path: &Path,
+    _password: &SecretRef,
+) -> anyhow::Result<SecretManager> {
+    anyhow::bail!("Stronghold snapshots require the `stronghold` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{SecretRef, SecretSource};
+
+    #[tokio::test]
+    async fn test_read_secret_from_env() {
+        std::env::set_var("DIDANCHOR_TEST_SECRET", " secret\n");
+        let secret = SecretRef::Env("DIDANCHOR_TEST_SECRET".to_owned());
+        assert_eq!(secret.read().await.unwrap(), "secret");
+
+        let missing = SecretRef::Env("DIDANCHOR_TEST_MISSING_SECRET".to_owned());
+        assert!(missing.read().await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_read_secret_from_file_checks_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("didanchor-secret-{}", rand::random::<u64>()));
+        tokio::fs::write(&path, "secret\n").await.unwrap();
+        let secret = SecretRef::File(path.clone());
+
+        tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))
+            .await
+            .unwrap();
+        assert!(secret.read().await.is_err());
+
+        tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .await
+            .unwrap();
+        assert_eq!(secret.read().await.unwrap(), "secret");
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[test]
+    fn test_secret_source_refers_to_secret() {
+        let source: SecretSource = toml::from_str(
+            r#"
+            type = "stronghold"
+            snapshot_path = "anchor.stronghold"
+            password = { env = "STRONGHOLD_PASSWORD" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            source,
+            SecretSource::Stronghold {
+                snapshot_path: PathBuf::from("anchor.stronghold"),
+                password: SecretRef::Env("STRONGHOLD_PASSWORD".to_owned()),
+            }
+        );
+    }
+}
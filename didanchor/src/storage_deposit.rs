@@ -0,0 +1,457 @@
+use iota_client::{
+    block::{
+        address::Address,
+        input::UtxoInput,
+        output::{
+            unlock_condition::AddressUnlockCondition, AliasId, AliasOutput, AliasOutputBuilder,
+            BasicOutputBuilder, Output, OutputId, RentStructure, UnlockCondition,
+        },
+    },
+    node_api::indexer::query_parameters::QueryParameter,
+};
+
+use crate::{resolve_alias_output, AliasContent, AnchorOutput};
+
+/// The storage deposit needed to publish alias content, estimated before publishing it.
+///
+/// Blocks don't pay fees, so the deposit is the only cost of publishing.
+/// It is locked in the Alias Output rather than spent, and returned if the output shrinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DepositEstimate {
+    /// The minimum storage deposit of the Alias Output with the content.
+    pub required_deposit: u64,
+    /// The deposit locked in the current Alias Output, which is reused.
+    pub current_deposit: u64,
+    /// The funds held by the basic outputs of the state controller.
+    pub available_funds: u64,
+}
+
+impl DepositEstimate {
+    /// Returns the funds the state controller has to add to the deposit.
+    pub fn additional_funds(&self) -> u64 {
+        self.required_deposit.saturating_sub(self.current_deposit)
+    }
+
+    /// Returns whether the state controller can cover the deposit.
+    pub fn is_covered(&self) -> bool {
+        self.additional_funds() <= self.available_funds
+    }
+}
+
+/// The storage deposit locked in the current Alias Output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DepositReport {
+    pub alias_id: AliasId,
+    /// The amount of base tokens held by the Alias Output.
+    pub locked: u64,
+    /// The minimum storage deposit of the Alias Output.
+    pub minimum: u64,
+    /// The funds held by the basic outputs of the state controller.
+    pub available_funds: u64,
+}
+
+impl DepositReport {
+    /// Returns the locked tokens that exceed the minimum.
+    ///
+    /// The next publish returns them to the state controller, unless they don't cover the storage deposit
+    /// of the output that would return them, in which case they stay locked in the Alias Output.
+    pub fn excess(&self) -> u64 {
+        self.locked.saturating_sub(self.minimum)
+    }
+}
+
+/// Publishing failed because the state controller can't cover the storage deposit.
+///
+/// Returned wrapped in an [`anyhow::Error`] and can be obtained through [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsufficientFunds {
+    /// The bech32 address of the state controller.
+    pub address: String,
+    /// The amount of base tokens needed from the state controller's basic outputs.
+    pub required: u64,
+    /// The amount of base tokens held by the state controller's basic outputs.
+    pub available: u64,
+}
+
+impl std::fmt::Display for InsufficientFunds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the storage deposit requires {} base tokens from {}, but only {} are available",
+            self.required, self.address, self.available
+        )
+    }
+}
+
+impl std::error::Error for InsufficientFunds {}
+
+/// The basic outputs that fund an Alias Output and the amount of base tokens it locks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FundSelection {
+    inputs: Vec<OutputId>,
+    alias_amount: u64,
+}
+
+/// Selects which of the `funds` add to the `current_amount` of the Alias Output, so that it can lock
+/// the `required` deposit.
+///
+/// Whatever the inputs hold beyond the deposit is returned in a remainder, which needs a storage deposit of
+/// its own, the `remainder_deposit`. Excess that doesn't cover it is locked in the Alias Output instead.
+///
+/// Fails with the amount that is needed from the `funds` if they don't suffice.
+fn select_funds(
+    required: u64,
+    current_amount: u64,
+    mut funds: Vec<(OutputId, u64)>,
+    remainder_deposit: u64,
+) -> Result<FundSelection, u64> {
+    let missing: u64 = required.saturating_sub(current_amount);
+    let is_enough = |selected: u64| {
+        selected == missing || selected >= missing.saturating_add(remainder_deposit)
+    };
+
+    // Selecting the largest outputs first keeps the transaction small.
+    funds.sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+
+    let mut inputs: Vec<OutputId> = Vec::new();
+    let mut selected: u64 = 0;
+
+    for (output_id, amount) in funds {
+        if is_enough(selected) {
+            break;
+        }
+
+        inputs.push(output_id);
+        selected += amount;
+    }
+
+    if selected < missing {
+        return Err(missing);
+    }
+
+    let total: u64 = current_amount + selected;
+    let excess: u64 = total - required;
+
+    let alias_amount: u64 = if excess == 0 || excess >= remainder_deposit {
+        required
+    } else {
+        total
+    };
+
+    Ok(FundSelection {
+        inputs,
+        alias_amount,
+    })
+}
+
+impl AnchorOutput {
+    /// Estimates the storage deposit of publishing `content`, without publishing it.
+    pub async fn estimate_deposit(
+        &self,
+        content: &AliasContent,
+    ) -> anyhow::Result<DepositEstimate> {
+        let rent_structure: RentStructure = self.client.get_rent_structure().await?;
+
+        let alias_output: AliasOutput = if self.alias_id.is_null() {
+            self.new_output(content.to_state_metadata()?, rent_structure)
+                .await?
+        } else {
+            self.update_output(content.to_state_metadata()?, rent_structure, self.alias_id)
+                .await?
+        };
+
+        let current_deposit: u64 = self
+            .current_output()
+            .await?
+            .map(|(_, output)| output.amount())
+            .unwrap_or(0);
+
+        Ok(DepositEstimate {
+            required_deposit: alias_output.amount(),
+            current_deposit,
+            available_funds: self.funds().await?.iter().map(|(_, amount)| amount).sum(),
+        })
+    }
+
+    /// Reports the storage deposit locked in the current Alias Output.
+    pub async fn deposit_report(&self) -> anyhow::Result<DepositReport> {
+        let rent_structure: RentStructure = self.client.get_rent_structure().await?;
+
+        let (_, current_output) = self
+            .current_output()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no output found for alias id {}", self.alias_id))?;
+
+        let minimum: u64 = AliasOutputBuilder::from(&current_output)
+            .with_minimum_storage_deposit(rent_structure)
+            .finish()?
+            .amount();
+
+        Ok(DepositReport {
+            alias_id: self.alias_id,
+            locked: current_output.amount(),
+            minimum,
+            available_funds: self.funds().await?.iter().map(|(_, amount)| amount).sum(),
+        })
+    }
+
+    /// Selects the inputs that fund `alias_output`: the current Alias Output, if any,
+    /// and as many basic outputs of the state controller as the rest of the deposit requires.
+    /// Returns them together with the output to publish, which keeps any excess that can't be returned.
+    ///
+    /// Fails with [`InsufficientFunds`] if the state controller can't cover the deposit.
+    pub(crate) async fn select_inputs(
+        &self,
+        alias_output: AliasOutput,
+        rent_structure: RentStructure,
+    ) -> anyhow::Result<(Vec<UtxoInput>, AliasOutput)> {
+        let mut inputs: Vec<UtxoInput> = Vec::new();
+        let mut current_amount: u64 = 0;
+
+        if let Some((output_id, current_output)) = self.current_output().await? {
+            inputs.push(UtxoInput::from(output_id));
+            current_amount = current_output.amount();
+        }
+
+        let funds: Vec<(OutputId, u64)> = if alias_output.amount() > current_amount {
+            self.funds().await?
+        } else {
+            Vec::new()
+        };
+
+        let available: u64 = funds.iter().map(|(_, amount)| amount).sum();
+        let remainder_deposit: u64 = self.remainder_deposit(rent_structure).await?;
+
+        let selection: FundSelection = match select_funds(
+            alias_output.amount(),
+            current_amount,
+            funds,
+            remainder_deposit,
+        ) {
+            Ok(selection) => selection,
+            Err(required) => {
+                return Err(InsufficientFunds {
+                    address: self.bech32_address().await?,
+                    required,
+                    available,
+                }
+                .into())
+            }
+        };
+
+        log::debug!(
+            "funding a storage deposit of {} base tokens with {} input(s), locking {}",
+            alias_output.amount(),
+            selection.inputs.len(),
+            selection.alias_amount
+        );
+
+        inputs.extend(selection.inputs.into_iter().map(UtxoInput::from));
+
+        let alias_output: AliasOutput = if selection.alias_amount == alias_output.amount() {
+            alias_output
+        } else {
+            AliasOutputBuilder::from(&alias_output)
+                .with_amount(selection.alias_amount)?
+                .finish()?
+        };
+
+        Ok((inputs, alias_output))
+    }
+
+    /// Returns the current Alias Output together with its id, if the alias exists.
+    async fn current_output(&self) -> anyhow::Result<Option<(OutputId, AliasOutput)>> {
+        if self.alias_id.is_null() {
+            return Ok(None);
+        }
+
+        Ok(resolve_alias_output(&self.client, self.alias_id)
+            .await?
+            .map(|(_, output_id, output)| (output_id, output)))
+    }
+
+    /// Returns the basic outputs of the state controller that can be spent without conditions, with their amounts.
+    async fn funds(&self) -> anyhow::Result<Vec<(OutputId, u64)>> {
+        let output_ids: Vec<OutputId> = self
+            .client
+            .basic_output_ids(vec![
+                QueryParameter::Address(self.bech32_address().await?),
+                QueryParameter::HasExpiration(false),
+                QueryParameter::HasTimelock(false),
+                QueryParameter::HasStorageDepositReturn(false),
+            ])
+            .await?;
+
+        let responses = futures::future::try_join_all(
+            output_ids
+                .iter()
+                .map(|output_id| self.client.get_output(output_id)),
+        )
+        .await?;
+
+        output_ids
+            .into_iter()
+            .zip(responses)
+            .filter(|(_, response)| !response.metadata.is_spent)
+            .map(|(output_id, response)| {
+                Ok((output_id, Output::try_from(&response.output)?.amount()))
+            })
+            .collect()
+    }
+
+    /// Returns the minimum storage deposit of a basic output that returns tokens to the state controller.
+    async fn remainder_deposit(&self, rent_structure: RentStructure) -> anyhow::Result<u64> {
+        let address: Address = self.address().await?;
+
+        Ok(
+            BasicOutputBuilder::new_with_minimum_storage_deposit(rent_structure)?
+                .add_unlock_condition(UnlockCondition::Address(AddressUnlockCondition::new(
+                    address,
+                )))
+                .finish()?
+                .amount(),
+        )
+    }
+
+    async fn bech32_address(&self) -> anyhow::Result<String> {
+        Ok(self
+            .address()
+            .await?
+            .to_bech32(self.client.get_bech32_hrp().await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iota_client::block::{
+        output::{AliasId, OutputId},
+        payload::transaction::TransactionId,
+    };
+
+    use super::{select_funds, DepositEstimate, DepositReport, FundSelection, InsufficientFunds};
+
+    const REMAINDER_DEPOSIT: u64 = 42_000;
+
+    fn output_id(n: u8) -> OutputId {
+        OutputId::new(TransactionId::new([n; 32]), 0).unwrap()
+    }
+
+    #[test]
+    fn test_deposit_estimate_and_report() {
+        let estimate = DepositEstimate {
+            required_deposit: 60_000,
+            current_deposit: 50_000,
+            available_funds: 5_000,
+        };
+
+        assert_eq!(estimate.additional_funds(), 10_000);
+        assert!(!estimate.is_covered());
+
+        let shrinking = DepositEstimate {
+            required_deposit: 40_000,
+            ..estimate
+        };
+        assert_eq!(shrinking.additional_funds(), 0);
+        assert!(shrinking.is_covered());
+
+        let report = DepositReport {
+            alias_id: AliasId::null(),
+            locked: 60_000,
+            minimum: 50_000,
+            available_funds: 0,
+        };
+        assert_eq!(report.excess(), 10_000);
+    }
+
+    #[test]
+    fn test_select_funds_exact_match() {
+        let funds = vec![(output_id(1), 10_000), (output_id(2), 20_000)];
+
+        // The largest output covers the missing deposit exactly, so no remainder is needed.
+        assert_eq!(
+            select_funds(70_000, 50_000, funds.clone(), REMAINDER_DEPOSIT),
+            Ok(FundSelection {
+                inputs: vec![output_id(2)],
+                alias_amount: 70_000,
+            })
+        );
+
+        // The current output already covers the deposit.
+        assert_eq!(
+            select_funds(50_000, 50_000, funds, REMAINDER_DEPOSIT),
+            Ok(FundSelection {
+                inputs: Vec::new(),
+                alias_amount: 50_000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_select_funds_remainder_below_minimum_deposit() {
+        let funds = vec![(output_id(1), 100_000), (output_id(2), 30_000)];
+
+        // 30_000 of the first output would be left over, which can't cover a remainder,
+        // so the second output is selected as well.
+        assert_eq!(
+            select_funds(120_000, 50_000, funds.clone(), REMAINDER_DEPOSIT),
+            Ok(FundSelection {
+                inputs: vec![output_id(1), output_id(2)],
+                alias_amount: 120_000,
+            })
+        );
+
+        // Without more funds, the leftover is locked in the Alias Output instead.
+        assert_eq!(
+            select_funds(120_000, 50_000, funds[..1].to_vec(), REMAINDER_DEPOSIT),
+            Ok(FundSelection {
+                inputs: vec![output_id(1)],
+                alias_amount: 150_000,
+            })
+        );
+
+        // The same goes for the excess of a shrinking Alias Output.
+        assert_eq!(
+            select_funds(40_000, 50_000, Vec::new(), REMAINDER_DEPOSIT),
+            Ok(FundSelection {
+                inputs: Vec::new(),
+                alias_amount: 50_000,
+            })
+        );
+        assert_eq!(
+            select_funds(40_000, 90_000, Vec::new(), REMAINDER_DEPOSIT),
+            Ok(FundSelection {
+                inputs: Vec::new(),
+                alias_amount: 40_000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_select_funds_insufficient_funds() {
+        let funds = vec![(output_id(1), 10_000), (output_id(2), 20_000)];
+
+        // Reports the amount missing from the current output.
+        assert_eq!(
+            select_funds(90_000, 50_000, funds, REMAINDER_DEPOSIT),
+            Err(40_000)
+        );
+        assert_eq!(
+            select_funds(90_000, 0, Vec::new(), REMAINDER_DEPOSIT),
+            Err(90_000)
+        );
+    }
+
+    #[test]
+    fn test_insufficient_funds_can_be_downcast() {
+        let err: anyhow::Error = InsufficientFunds {
+            address: "rms1...".to_owned(),
+            required: 10_000,
+            available: 5_000,
+        }
+        .into();
+
+        let insufficient = err.downcast_ref::<InsufficientFunds>().unwrap();
+        assert_eq!(insufficient.required, 10_000);
+        assert_eq!(insufficient.available, 5_000);
+    }
+}
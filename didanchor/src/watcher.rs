@@ -0,0 +1,520 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use identity_core::convert::FromJson;
+use identity_iota_core::did::IotaDID;
+use iota_client::block::output::AliasId;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::{
+    background_task::{BackgroundTask, EventSender},
+    AliasContent, ContentStore, DIDIndex, IpfsNodePublicAddress, LedgerAnchor,
+};
+
+/// A change of an alias observed by a [`Watcher`].
+///
+/// Every new state is announced with [`AliasEvent::StateIndexChanged`],
+/// followed by an event for each part of the content that changed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AliasEvent {
+    /// The alias has a new state. `previous` is `None` for the first state observed by the watcher.
+    StateIndexChanged {
+        alias_id: AliasId,
+        previous: Option<u32>,
+        current: u32,
+    },
+    /// A new index was anchored.
+    IndexCidChanged {
+        alias_id: AliasId,
+        previous: Option<String>,
+        current: String,
+    },
+    /// DIDs were added to, updated in or removed from the index.
+    DidsChanged {
+        alias_id: AliasId,
+        changes: DidChanges,
+    },
+    /// IPFS nodes that serve the content were added or removed.
+    IpfsNodesChanged {
+        alias_id: AliasId,
+        added: Vec<IpfsNodePublicAddress>,
+        removed: Vec<IpfsNodePublicAddress>,
+    },
+}
+
+impl AliasEvent {
+    pub fn alias_id(&self) -> AliasId {
+        match self {
+            AliasEvent::StateIndexChanged { alias_id, .. }
+            | AliasEvent::IndexCidChanged { alias_id, .. }
+            | AliasEvent::DidsChanged { alias_id, .. }
+            | AliasEvent::IpfsNodesChanged { alias_id, .. } => *alias_id,
+        }
+    }
+}
+
+/// The difference between two indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct DidChanges {
+    pub added: Vec<IotaDID>,
+    /// DIDs whose chain of custody changed.
+    pub updated: Vec<IotaDID>,
+    pub removed: Vec<IotaDID>,
+}
+
+impl DidChanges {
+    /// Computes the changes from the `previous` to the `current` index, with the DIDs of each kind sorted.
+    pub fn between(previous: &DIDIndex, current: &DIDIndex) -> Self {
+        let mut changes = DidChanges::default();
+
+        for (did, cid) in current {
+            match previous.get(did) {
+                None => changes.added.push(did.clone()),
+                Some(previous_cid) if previous_cid != cid => changes.updated.push(did.clone()),
+                Some(_) => (),
+            }
+        }
+
+        changes.removed = previous
+            .keys()
+            .filter(|did| !current.contains_key(*did))
+            .cloned()
+            .collect();
+
+        for dids in [
+            &mut changes.added,
+            &mut changes.updated,
+            &mut changes.removed,
+        ] {
+            dids.sort_by_key(|did| did.to_string());
+        }
+
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The last state of an alias that a [`Watcher`] observed.
+struct ObservedState {
+    state_index: u32,
+    content: AliasContent,
+    index: DIDIndex,
+}
+
+/// Watches a set of aliases and emits an [`AliasEvent`] for every change of their content.
+///
+/// The aliases are polled regularly. With the `mqtt` feature, [`Watcher::spawn_with_mqtt`] additionally
+/// checks an alias as soon as the node announces a new output for it, while polling picks up
+/// anything that was missed, e.g. during a reconnect to the broker.
+pub struct Watcher {
+    task: BackgroundTask<AliasEvent, ()>,
+    #[cfg(feature = "mqtt")]
+    mqtt: Option<(iota_client::Client, Vec<iota_client::node_api::mqtt::Topic>)>,
+}
+
+impl Watcher {
+    const EVENT_CAPACITY: usize = 256;
+
+    /// Spawns a task on the current runtime that polls the `ledger` for new states of the aliases
+    /// every `poll_interval` and reads their indices from the `content_store`.
+    ///
+    /// The first state observed of each alias is reported as well, compared to an empty one.
+    pub fn spawn(
+        ledger: impl LedgerAnchor + 'static,
+        content_store: impl ContentStore + 'static,
+        alias_ids: Vec<AliasId>,
+        poll_interval: Duration,
+    ) -> Self {
+        // Without another source of notifications, the sender is dropped and only polling remains.
+        let (_, notifications) = mpsc::unbounded_channel();
+
+        Self::spawn_with_notifications(
+            Arc::new(ledger),
+            Arc::new(content_store),
+            alias_ids,
+            poll_interval,
+            notifications,
+        )
+    }
+
+    /// Like [`Watcher::spawn`], but also subscribes to the new outputs of the aliases
+    /// through the MQTT event API of the node that `client` is connected to.
+    #[cfg(feature = "mqtt")]
+    pub async fn spawn_with_mqtt(
+        client: iota_client::Client,
+        ledger: impl LedgerAnchor + 'static,
+        content_store: impl ContentStore + 'static,
+        alias_ids: Vec<AliasId>,
+        poll_interval: Duration,
+    ) -> anyhow::Result<Self> {
+        let (sender, notifications) = mpsc::unbounded_channel();
+
+        let topics: Vec<iota_client::node_api::mqtt::Topic> = alias_ids
+            .iter()
+            .map(|alias_id| {
+                iota_client::node_api::mqtt::Topic::try_from(format!("outputs/aliases/{alias_id}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        for (topic, alias_id) in topics.iter().zip(alias_ids.iter().copied()) {
+            let sender = sender.clone();
+
+            // The payload is not needed, since the alias is checked through the ledger anyway.
+            client
+                .subscribe(vec![topic.clone()], move |_| {
+                    let _ = sender.send(alias_id);
+                })
+                .await?;
+        }
+
+        let mut watcher = Self::spawn_with_notifications(
+            Arc::new(ledger),
+            Arc::new(content_store),
+            alias_ids,
+            poll_interval,
+            notifications,
+        );
+        watcher.mqtt = Some((client, topics));
+
+        Ok(watcher)
+    }
+
+    fn spawn_with_notifications(
+        ledger: Arc<dyn LedgerAnchor>,
+        content_store: Arc<dyn ContentStore>,
+        alias_ids: Vec<AliasId>,
+        poll_interval: Duration,
+        notifications: mpsc::UnboundedReceiver<AliasId>,
+    ) -> Self {
+        let inspector = Inspector {
+            ledger,
+            content_store,
+            observed: HashMap::new(),
+        };
+
+        let task = BackgroundTask::spawn(Self::EVENT_CAPACITY, |events, shutdown| {
+            Self::run(
+                inspector,
+                alias_ids,
+                poll_interval,
+                notifications,
+                events,
+                shutdown,
+            )
+        });
+
+        Self {
+            task,
+            #[cfg(feature = "mqtt")]
+            mqtt: None,
+        }
+    }
+
+    /// Returns a receiver that is sent every event from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<AliasEvent> {
+        self.task.subscribe()
+    }
+
+    /// Stops watching the aliases.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        #[cfg(feature = "mqtt")]
+        if let Some((client, topics)) = self.mqtt {
+            if let Err(err) = client.unsubscribe(topics).await {
+                log::warn!("failed to unsubscribe from the alias topics: {err}");
+            }
+        }
+
+        self.task.shutdown().await
+    }
+
+    async fn run(
+        mut inspector: Inspector,
+        alias_ids: Vec<AliasId>,
+        poll_interval: Duration,
+        mut notifications: mpsc::UnboundedReceiver<AliasId>,
+        events: EventSender<AliasEvent>,
+        mut shutdown: oneshot::Receiver<()>,
+    ) {
+        let mut ticker = tokio::time::interval(poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    for alias_id in alias_ids.iter() {
+                        inspector.inspect(*alias_id, &events).await;
+                    }
+                }
+                Some(alias_id) = notifications.recv() => {
+                    inspector.inspect(alias_id, &events).await;
+                }
+                _ = &mut shutdown => break,
+            }
+        }
+    }
+}
+
+/// Compares the current states of aliases to the ones observed before.
+struct Inspector {
+    ledger: Arc<dyn LedgerAnchor>,
+    content_store: Arc<dyn ContentStore>,
+    observed: HashMap<AliasId, ObservedState>,
+}
+
+impl Inspector {
+    async fn inspect(&mut self, alias_id: AliasId, events: &EventSender<AliasEvent>) {
+        match self.changes(alias_id).await {
+            Ok(changes) => {
+                for event in changes {
+                    events.send(event);
+                }
+            }
+            Err(err) => log::warn!("failed to inspect alias {alias_id}: {err:#}"),
+        }
+    }
+
+    /// Returns the events of the changes since the last observed state of the alias and records the current one.
+    async fn changes(&mut self, alias_id: AliasId) -> anyhow::Result<Vec<AliasEvent>> {
+        let state_index: u32 = match self.ledger.state_index(alias_id).await? {
+            Some(state_index) => state_index,
+            None => return Ok(Vec::new()),
+        };
+
+        let previous: Option<&ObservedState> = self.observed.get(&alias_id);
+
+        if previous.map(|previous| previous.state_index) == Some(state_index) {
+            return Ok(Vec::new());
+        }
+
+        let content: AliasContent = self
+            .ledger
+            .read_content(alias_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no content found for alias id {alias_id}"))?;
+
+        // A new state can keep the index, e.g. after an empty commit, in which case it is reused.
+        let index: DIDIndex = match previous {
+            Some(previous) if previous.content.index_cid == content.index_cid => {
+                previous.index.clone()
+            }
+            _ => DIDIndex::from_json_slice(&self.content_store.get(&content.index_cid).await?)?,
+        };
+
+        let current = ObservedState {
+            state_index,
+            content,
+            index,
+        };
+        let events: Vec<AliasEvent> = diff(alias_id, previous, &current);
+
+        self.observed.insert(alias_id, current);
+
+        Ok(events)
+    }
+}
+
+fn diff(
+    alias_id: AliasId,
+    previous: Option<&ObservedState>,
+    current: &ObservedState,
+) -> Vec<AliasEvent> {
+    let mut events: Vec<AliasEvent> = vec![AliasEvent::StateIndexChanged {
+        alias_id,
+        previous: previous.map(|previous| previous.state_index),
+        current: current.state_index,
+    }];
+
+    let previous_index_cid: Option<&String> = previous.map(|previous| &previous.content.index_cid);
+
+    if previous_index_cid != Some(&current.content.index_cid) {
+        events.push(AliasEvent::IndexCidChanged {
+            alias_id,
+            previous: previous_index_cid.cloned(),
+            current: current.content.index_cid.clone(),
+        });
+    }
+
+    let empty_index = DIDIndex::new();
+    let changes: DidChanges = DidChanges::between(
+        previous
+            .map(|previous| &previous.index)
+            .unwrap_or(&empty_index),
+        &current.index,
+    );
+
+    if !changes.is_empty() {
+        events.push(AliasEvent::DidsChanged { alias_id, changes });
+    }
+
+    let previous_nodes: &[IpfsNodePublicAddress] = previous
+        .map(|previous| previous.content.ipfs_node_addrs.as_slice())
+        .unwrap_or_default();
+    let current_nodes: &[IpfsNodePublicAddress] = &current.content.ipfs_node_addrs;
+
+    let added: Vec<IpfsNodePublicAddress> = current_nodes
+        .iter()
+        .filter(|node| !previous_nodes.contains(node))
+        .cloned()
+        .collect();
+    let removed: Vec<IpfsNodePublicAddress> = previous_nodes
+        .iter()
+        .filter(|node| !current_nodes.contains(node))
+        .cloned()
+        .collect();
+
+    if !added.is_empty() || !removed.is_empty() {
+        events.push(AliasEvent::IpfsNodesChanged {
+            alias_id,
+            added,
+            removed,
+        });
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::sync::Arc;
+
+    use identity_core::{
+        convert::ToJson,
+        crypto::{KeyPair, KeyType},
+    };
+    use identity_iota_core::{did::IotaDID, document::IotaDocument};
+    use iota_client::block::output::AliasId;
+    use tokio::sync::broadcast;
+
+    use super::{AliasEvent, DidChanges, Watcher};
+    use crate::{AliasContent, ContentStore, DIDIndex, LedgerAnchor, MemoryStore, MockLedger};
+
+    fn new_did() -> IotaDID {
+        let keypair: KeyPair = KeyPair::new(KeyType::Ed25519).unwrap();
+        IotaDocument::new(&keypair).unwrap().id().clone()
+    }
+
+    #[test]
+    fn test_did_changes_between_indices() {
+        let (first, second, third) = (new_did(), new_did(), new_did());
+
+        let previous: DIDIndex = [
+            (first.clone(), "a".to_owned()),
+            (second.clone(), "b".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let current: DIDIndex = [
+            (second.clone(), "c".to_owned()),
+            (third.clone(), "d".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+
+        let changes = DidChanges::between(&previous, &current);
+        assert_eq!(changes.added, vec![third]);
+        assert_eq!(changes.updated, vec![second]);
+        assert_eq!(changes.removed, vec![first]);
+
+        assert!(DidChanges::between(&current, &current).is_empty());
+    }
+
+    async fn next_events(
+        receiver: &mut broadcast::Receiver<AliasEvent>,
+        count: usize,
+    ) -> Vec<AliasEvent> {
+        let mut events = Vec::new();
+
+        for _ in 0..count {
+            let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("the watcher should emit an event")
+                .unwrap();
+            events.push(event);
+        }
+
+        events
+    }
+
+    #[tokio::test]
+    async fn test_watcher_emits_changes() {
+        let mut ledger = MockLedger::new();
+        let store = Arc::new(MemoryStore::new());
+        let (first, second) = (new_did(), new_did());
+
+        let first_index: DIDIndex = [(first.clone(), "a".to_owned())].into_iter().collect();
+        let first_cid: String = store.add(first_index.to_json_vec().unwrap()).await.unwrap();
+        let alias_id: AliasId = ledger
+            .publish_content(
+                AliasId::null(),
+                AliasContent::new(first_cid.clone(), Vec::new(), vec![0; 32]),
+            )
+            .await
+            .unwrap()
+            .alias_id;
+
+        let watcher = Watcher::spawn(
+            ledger.clone(),
+            Arc::clone(&store),
+            vec![alias_id],
+            Duration::from_millis(10),
+        );
+        let mut receiver = watcher.subscribe();
+
+        let events = next_events(&mut receiver, 3).await;
+        assert_eq!(
+            events[0],
+            AliasEvent::StateIndexChanged {
+                alias_id,
+                previous: None,
+                current: 0
+            }
+        );
+        assert!(
+            matches!(events[2], AliasEvent::DidsChanged { ref changes, .. } if changes.added == vec![first.clone()])
+        );
+
+        let second_index: DIDIndex = [(second.clone(), "b".to_owned())].into_iter().collect();
+        let second_cid: String = store
+            .add(second_index.to_json_vec().unwrap())
+            .await
+            .unwrap();
+        ledger
+            .publish_content(
+                alias_id,
+                AliasContent::new(second_cid.clone(), Vec::new(), vec![0; 32]),
+            )
+            .await
+            .unwrap();
+
+        let events = next_events(&mut receiver, 3).await;
+        assert_eq!(
+            events,
+            vec![
+                AliasEvent::StateIndexChanged {
+                    alias_id,
+                    previous: Some(0),
+                    current: 1
+                },
+                AliasEvent::IndexCidChanged {
+                    alias_id,
+                    previous: Some(first_cid),
+                    current: second_cid
+                },
+                AliasEvent::DidsChanged {
+                    alias_id,
+                    changes: DidChanges {
+                        added: vec![second],
+                        updated: Vec::new(),
+                        removed: vec![first],
+                    }
+                },
+            ]
+        );
+
+        watcher.shutdown().await.unwrap();
+    }
+}
@@ -47,7 +47,13 @@ async fn main() -> anyhow::Result<()> {
 
     node.update_document(doc1.clone()).await?;
 
-    let alias_id = node.commit_changes().await?;
+    let receipt = node.commit_changes().await?;
+    let alias_id = receipt.alias_id();
+
+    println!(
+        "Anchored index {} in state {} of alias {alias_id}",
+        receipt.index_cid, receipt.ledger.state_index
+    );
 
     println!("Published DIDs:");
     for doc in [doc1, doc2, doc3, doc4] {
@@ -0,0 +1,41 @@
+use std::io::Write;
+
+use didanchor::{AnchorConfig, AuditProgress, AuditReport, Auditor};
+use iota_client::block::output::AliasId;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config: AnchorConfig = AnchorConfig::read_default_location().await?;
+
+    // Audit the configured alias unless another one is given.
+    let alias_id: AliasId = match std::env::args().nth(1) {
+        Some(alias_id) => alias_id.parse()?,
+        None => config.alias_id,
+    };
+
+    if alias_id.is_null() {
+        anyhow::bail!("expected an alias id as the argument");
+    }
+
+    let auditor = Auditor::new(&config.iota_endpoint, "http://127.0.0.1:5001")?;
+
+    // The progress goes to stderr, so that stdout only contains the report.
+    let report: AuditReport = auditor
+        .audit(alias_id, |progress: AuditProgress| {
+            eprint!(
+                "\rchecked {}/{} chains of custody",
+                progress.checked, progress.total
+            );
+            let _ = std::io::stderr().flush();
+        })
+        .await?;
+    eprintln!();
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
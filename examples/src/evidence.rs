@@ -0,0 +1,42 @@
+use didanchor::{verify_evidence, AnchorConfig, EvidenceBundle, MilestoneCommittee, Resolver};
+use identity_core::convert::ToJson;
+use identity_did::did::CoreDID;
+
+/// Exports the evidence for a DID and verifies it against the milestone committee of the network,
+/// which is read from a JSON file as given in the protocol parameters of the network's nodes.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+
+    let (did, committee_path): (CoreDID, String) = match (args.next(), args.next()) {
+        (Some(did), Some(committee_path)) => (CoreDID::parse(did)?, committee_path),
+        _ => anyhow::bail!(
+            "expected a `did:iota:<alias_id>:<tag>` and the path to a milestone committee as arguments"
+        ),
+    };
+
+    let committee: MilestoneCommittee =
+        serde_json::from_slice(&tokio::fs::read(&committee_path).await?)?;
+
+    let config: AnchorConfig = AnchorConfig::read_default_location().await?;
+
+    let resolver = Resolver::new(&config.iota_endpoint, "http://127.0.0.1:5001")?
+        .with_milestone_committee(committee.clone());
+
+    let evidence: EvidenceBundle = match resolver.export_evidence(&did).await? {
+        Some(evidence) => evidence,
+        None => anyhow::bail!("{did} is not published by its alias"),
+    };
+
+    println!("{}", evidence.to_json_pretty()?);
+
+    // This is what a third party does with the bundle, without access to the network.
+    let verified = verify_evidence(&evidence, &committee)?;
+
+    println!(
+        "Verified {did} as of milestone {} ({})",
+        verified.milestone_index, verified.milestone_timestamp
+    );
+
+    Ok(())
+}
@@ -1,6 +1,11 @@
 use didanchor::AnchorConfig;
+use didanchor::ContentStoreConfig;
 use didanchor::IpfsNodeManagementAddress;
 use didanchor::IpfsNodePublicAddress;
+use didanchor::LedgerConfig;
+use didanchor::RetentionPolicy;
+use didanchor::SecretRef;
+use didanchor::SecretSource;
 use identity_core::crypto::KeyType;
 use iota_client::block::address::Address;
 use iota_client::block::output::AliasId;
@@ -17,6 +22,7 @@ use url::Url;
 
 static DEFAULT_ENDPOINT: &str = "https://api.testnet.shimmer.network/";
 static FAUCET_URL: &str = "https://faucet.testnet.shimmer.network/api/enqueue";
+static MNEMONIC_PATH: &str = "./anchor_mnemonic";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -25,6 +31,7 @@ async fn main() -> anyhow::Result<()> {
         .finish()?;
 
     let (mnemonic, _, _) = get_address_with_funds(&client).await?;
+    write_secret(MNEMONIC_PATH, &mnemonic)?;
 
     // Ports match docker-compose.yml.
     let swarm_port: u16 = 4002;
@@ -91,15 +98,42 @@ async fn main() -> anyhow::Result<()> {
 
     let config = AnchorConfig {
         alias_id: AliasId::null(),
-        mnemonic,
+        secret: SecretSource::Mnemonic {
+            mnemonic: SecretRef::File(MNEMONIC_PATH.into()),
+        },
+        mnemonic: None,
+        governor_address: None,
         iota_endpoint: DEFAULT_ENDPOINT.to_owned(),
         ipfs_node_public_addrs: pub_node_urls,
         ipfs_node_management_addrs: mgmt_node_urls,
+        journal_path: AnchorConfig::default_journal_path(),
+        pin_gc_path: AnchorConfig::default_pin_gc_path(),
+        retention: RetentionPolicy::default(),
+        content_store: ContentStoreConfig::default(),
+        ledger: LedgerConfig::default(),
     };
 
     config.write_default_location().await?;
 
-    println!("initialized {}", AnchorConfig::DEFAULT_PATH);
+    println!(
+        "initialized {} with the mnemonic in {MNEMONIC_PATH}",
+        AnchorConfig::DEFAULT_PATH
+    );
+
+    Ok(())
+}
+
+/// Writes the `secret` to a new file that only its owner can access.
+fn write_secret(path: &str, secret: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(secret.as_bytes())?;
 
     Ok(())
 }
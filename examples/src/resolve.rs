@@ -6,13 +6,14 @@ use identity_did::did::CoreDID;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let mut args = std::env::args();
+    let dids: Vec<CoreDID> = std::env::args()
+        .skip(1)
+        .map(CoreDID::parse)
+        .collect::<Result<_, _>>()?;
 
-    let did: String = args.nth(1).ok_or_else(|| {
-        anyhow::anyhow!("expected a `did:iota:<alias_id>:<tag>` as the first argument")
-    })?;
-
-    let did: CoreDID = CoreDID::parse(did)?;
+    if dids.is_empty() {
+        anyhow::bail!("expected one or more `did:iota:<alias_id>:<tag>` as arguments");
+    }
 
     let config: AnchorConfig = AnchorConfig::read_default_location().await?;
 
@@ -20,15 +21,20 @@ async fn main() -> anyhow::Result<()> {
 
     let resolver = Resolver::new(&config.iota_endpoint, "http://127.0.0.1:5001")?;
 
-    match resolver.resolve(&did).await? {
-        Some(document) => {
-            println!("{}", document.to_json_pretty()?);
-            println!("Resolution took {}ms", time.elapsed().as_millis());
-        }
-        None => {
-            println!("Unable to resolve {did}");
+    let results = resolver.resolve_many(&dids).await;
+
+    for (did, result) in dids.iter().zip(results) {
+        match result? {
+            Some(document) => {
+                println!("{}", document.to_json_pretty()?);
+            }
+            None => {
+                println!("Unable to resolve {did}");
+            }
         }
     }
 
+    println!("Resolution took {}ms", time.elapsed().as_millis());
+
     Ok(())
 }
@@ -1,6 +1,9 @@
 use anyhow::Context;
 use rand::Rng;
-use reqwest::{Client, Response};
+use reqwest::{
+    multipart::{Form, Part},
+    Client, Response,
+};
 use url::Url;
 
 #[derive(Clone)]
@@ -68,6 +71,56 @@ impl IpfsClient {
         Ok(response.bytes().await?)
     }
 
+    /// Add `data` to IPFS and pin it.
+    ///
+    /// Returns the CID of the added content.
+    ///
+    /// <https://docs.ipfs.tech/reference/kubo/rpc/#api-v0-add>
+    pub async fn add(&self, data: Vec<u8>) -> anyhow::Result<String> {
+        let node_url: &Url = self.get_random_node();
+        let endpoint: Url = node_url.join("api/v0/add")?;
+
+        // The name doesn't matter.
+        let form = Form::new().part("file", Part::bytes(data));
+
+        let request = self
+            .client
+            .post(endpoint)
+            .multipart(form)
+            .query(&[
+                ("pin", "true"),
+                ("hash", "blake2b-256"),
+                ("cid-version", "1"),
+                ("progress", "false"),
+            ])
+            .build()?;
+
+        let response = self.execute_request(request).await.context("add failed")?;
+
+        let added: serde_json::Value = response.json().await?;
+
+        added["Hash"]
+            .as_str()
+            .map(ToOwned::to_owned)
+            .context("the add response did not contain a hash")
+    }
+
+    /// Remove the pin of `cid`.
+    ///
+    /// <https://docs.ipfs.tech/reference/kubo/rpc/#api-v0-pin-rm>
+    pub async fn pin_rm(&self, cid: &str) -> anyhow::Result<()> {
+        let node_url: &Url = self.get_random_node();
+        let endpoint: Url = node_url.join("api/v0/pin/rm")?;
+
+        let request = self.client.post(endpoint).query(&[("arg", cid)]).build()?;
+
+        self.execute_request(request)
+            .await
+            .context("pin rm failed")?;
+
+        Ok(())
+    }
+
     /// Output config file contents.
     ///
     /// <https://docs.ipfs.tech/reference/kubo/rpc/#api-v0-config-show>
@@ -1,11 +1,17 @@
+use std::collections::HashMap;
+
 use crate::AddResponse;
+use futures::{StreamExt, TryStreamExt};
 use rand::Rng;
 use reqwest::{
     multipart::{Form, Part},
-    Client,
+    Client, Response,
 };
 use url::Url;
 
+/// The maximum number of pin requests that are in flight at once.
+const MAX_CONCURRENT_PINS: usize = 16;
+
 #[derive(Debug, Clone)]
 pub struct IpfsCluster {
     client: Client,
@@ -30,11 +36,77 @@ impl IpfsCluster {
 
     /// Add `data` to the cluster and pin it to every peer.
     pub async fn add(&self, data: Vec<u8>) -> anyhow::Result<AddResponse> {
+        // The name doesn't matter.
+        let form = Form::new().part("_data", Part::bytes(data));
+
+        let response = self.execute_add(form).await?;
+        let add_response: AddResponse = response.json().await?;
+
+        Ok(add_response)
+    }
+
+    /// Add multiple files in a single request and pin them to every peer.
+    ///
+    /// Each file is given as a pair of a unique name and its content.
+    /// The responses are returned in the same order as the given files.
+    ///
+    /// The cluster only pins the last root of a multi-file add, so every file is pinned explicitly afterwards.
+    pub async fn add_many(
+        &self,
+        files: Vec<(String, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<AddResponse>> {
+        let names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+
+        let form = files.into_iter().fold(Form::new(), |form, (name, data)| {
+            form.part("file", Part::bytes(data).file_name(name))
+        });
+
+        let response = self.execute_add(form).await?;
+
+        // The response contains one JSON object per added file, separated by newlines.
+        let body: String = response.text().await?;
+        let mut add_responses: HashMap<String, AddResponse> = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str::<AddResponse>(line).map(|res| (res.name.clone(), res))
+            })
+            .collect::<Result<_, _>>()?;
+
+        if let Some(name) = names.iter().find(|name| !add_responses.contains_key(*name)) {
+            return Err(AddManyError {
+                error: anyhow::anyhow!("the cluster did not add `{name}`"),
+                added: add_responses.into_values().collect(),
+            }
+            .into());
+        }
+
+        let add_responses: Vec<AddResponse> = names
+            .iter()
+            .filter_map(|name| add_responses.remove(name))
+            .collect();
+
+        // Pinning content that is already pinned with the same options changes nothing.
+        let pinned: anyhow::Result<()> = futures::stream::iter(add_responses.iter())
+            .map(|add_response| self.pin(&add_response.cid))
+            .buffer_unordered(MAX_CONCURRENT_PINS)
+            .try_collect::<()>()
+            .await;
+
+        match pinned {
+            Ok(()) => Ok(add_responses),
+            Err(error) => Err(AddManyError {
+                error,
+                added: add_responses,
+            }
+            .into()),
+        }
+    }
+
+    async fn execute_add(&self, form: Form) -> anyhow::Result<Response> {
         let node_url: &Url = self.get_random_node();
         let endpoint: Url = node_url.join("add")?;
 
-        // The name doesn't matter.
-        let form = Form::new().part("_data", Part::bytes(data));
         let request = self
             .client
             .post(endpoint)
@@ -51,9 +123,41 @@ impl IpfsCluster {
         log::trace!("{request:?}");
 
         let response = self.client.execute(request).await?;
-        let add_response: AddResponse = response.json().await?;
 
-        Ok(add_response)
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(anyhow::anyhow!(
+                "failed to add with status {}",
+                response.status()
+            ))
+        }
+    }
+
+    /// Pins the content with the given `cid` to every peer, which fetch it from the IPFS network.
+    pub async fn pin(&self, cid: &str) -> anyhow::Result<()> {
+        let node_url: &Url = self.get_random_node();
+        let endpoint: Url = node_url.join("pins/ipfs/")?.join(cid)?;
+
+        let request = self
+            .client
+            .post(endpoint)
+            // -1 means pinning it to every peer in the cluster.
+            .query(&[("replication-min", "-1"), ("replication-max", "-1")])
+            .build()?;
+
+        log::trace!("{request:?}");
+
+        let response = self.client.execute(request).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "failed to pin with status {}",
+                response.status()
+            ))
+        }
     }
 
     /// Unpins the given `cid` from the cluster.
@@ -77,3 +181,26 @@ impl IpfsCluster {
         }
     }
 }
+
+/// Adding multiple files failed after the cluster added some of them.
+///
+/// The `added` files might be pinned, so they are returned for the caller to unpin them.
+/// Returned wrapped in an [`anyhow::Error`] and can be obtained through [`anyhow::Error::downcast_ref`].
+#[derive(Debug)]
+pub struct AddManyError {
+    pub added: Vec<AddResponse>,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for AddManyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "adding the files failed after {} of them were added: {:#}",
+            self.added.len(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for AddManyError {}
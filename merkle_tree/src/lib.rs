@@ -3,5 +3,6 @@ mod node;
 mod proof;
 mod tree;
 
+pub use digest_ext::DigestExt;
 pub use proof::*;
 pub use tree::*;
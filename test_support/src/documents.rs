@@ -0,0 +1,58 @@
+use identity_core::crypto::{KeyPair, KeyType};
+use identity_did::did::CoreDID;
+use identity_iota_client::{document::ResolvedIotaDocument, tangle::TangleRef};
+use identity_iota_core::{document::IotaDocument, tangle::MessageId};
+use iota_client::block::output::AliasId;
+
+/// Generates a new signed DID document together with its key pair.
+pub fn new_document() -> (KeyPair, ResolvedIotaDocument) {
+    let keypair: KeyPair = KeyPair::new(KeyType::Ed25519).expect("key generation should succeed");
+
+    let mut document: IotaDocument =
+        IotaDocument::new(&keypair).expect("the document should be valid");
+
+    sign(&keypair, &mut document);
+
+    let mut resolved = ResolvedIotaDocument::from(document);
+    resolved.set_message_id(MessageId::new(rand::random()));
+
+    (keypair, resolved)
+}
+
+/// Applies `f` to the document and signs the result as the next version of it.
+pub fn update_document(
+    keypair: &KeyPair,
+    mut resolved: ResolvedIotaDocument,
+    f: impl FnOnce(&mut IotaDocument),
+) -> ResolvedIotaDocument {
+    f(&mut resolved.document);
+
+    resolved.document.metadata.previous_message_id = *resolved.message_id();
+
+    sign(keypair, &mut resolved.document);
+
+    resolved.set_message_id(MessageId::new(rand::random()));
+
+    resolved
+}
+
+/// Returns the DID under which the document is resolvable once it is anchored in the alias with `alias_id`.
+pub fn anchored_did(alias_id: AliasId, resolved: &ResolvedIotaDocument) -> CoreDID {
+    CoreDID::parse(format!(
+        "did:iota:{alias_id}:{}",
+        resolved.document.id().tag()
+    ))
+    .expect("the DID should be valid")
+}
+
+fn sign(keypair: &KeyPair, document: &mut IotaDocument) {
+    let method_id = document
+        .default_signing_method()
+        .expect("the document should have a signing method")
+        .id()
+        .clone();
+
+    document
+        .sign_self(keypair.private(), method_id)
+        .expect("signing should succeed");
+}
@@ -0,0 +1,341 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Multipart, Path, Query},
+    http::StatusCode,
+    routing::post,
+    Extension, Json, Router,
+};
+use bytes::Bytes;
+use didanchor::{content_id, IpfsNodeManagementAddress};
+use ipfs_cluster::AddResponse;
+use url::Url;
+
+/// An in-memory stand-in for an IPFS cluster and its IPFS (Kubo) node.
+///
+/// Serves the parts of the cluster REST API used by [`IpfsCluster`](ipfs_cluster::IpfsCluster)
+/// and of the Kubo RPC API used by `IpfsClient` on the same port.
+/// CIDs are computed with [`content_id`], so they differ from the ones IPFS would assign.
+/// Without a network to fetch content from, pinning a CID only records it.
+/// Like the cluster, a multi-file add only pins the last file.
+#[derive(Clone)]
+pub struct FakeIpfs {
+    url: Url,
+    state: Arc<FakeIpfsState>,
+}
+
+#[derive(Default)]
+struct FakeIpfsState {
+    content: Mutex<HashMap<String, Bytes>>,
+    // CIDs that were pinned by reference rather than added.
+    pins: Mutex<HashSet<String>>,
+    // CIDs of content that was added, but not pinned.
+    unpinned: Mutex<HashSet<String>>,
+    failures: Mutex<Failures>,
+}
+
+/// The number of upcoming requests of each kind that fail.
+#[derive(Default)]
+struct Failures {
+    adds: usize,
+    pins: usize,
+    cats: usize,
+    unpins: usize,
+}
+
+impl FakeIpfs {
+    /// Starts the fake on a random local port.
+    pub fn start() -> anyhow::Result<Self> {
+        let state: Arc<FakeIpfsState> = Arc::new(FakeIpfsState::default());
+
+        let router = Router::new()
+            // IPFS cluster
+            .route("/add", post(cluster_add))
+            .route("/pins/ipfs/:cid", post(cluster_pin).delete(cluster_unpin))
+            // Kubo RPC
+            .route("/api/v0/add", post(kubo_add))
+            .route("/api/v0/cat", post(kubo_cat))
+            .route("/api/v0/pin/rm", post(kubo_pin_rm))
+            .route("/api/v0/swarm/connect", post(kubo_swarm_connect))
+            .layer(Extension(Arc::clone(&state)));
+
+        Ok(Self {
+            url: crate::serve(router)?,
+            state,
+        })
+    }
+
+    /// Returns the URL of both the cluster and the RPC API.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the address to configure an anchor with.
+    pub fn management_address(&self) -> IpfsNodeManagementAddress {
+        let port: u16 = self.url.port().expect("the url should have a port");
+
+        IpfsNodeManagementAddress {
+            host: self
+                .url
+                .host_str()
+                .expect("the url should have a host")
+                .to_owned(),
+            api_port: port,
+            cluster_port: port,
+        }
+    }
+
+    /// Returns whether content with the given `cid` is pinned.
+    pub fn contains(&self, cid: &str) -> bool {
+        (self.state.content().contains_key(cid) && !self.state.unpinned().contains(cid))
+            || self.state.pins().contains(cid)
+    }
+
+    /// Returns the CIDs of all pinned content.
+    pub fn pinned(&self) -> Vec<String> {
+        let content = self.state.content();
+        let unpinned = self.state.unpinned();
+        let mut pinned: Vec<String> = content
+            .keys()
+            .filter(|cid| !unpinned.contains(*cid))
+            .cloned()
+            .collect();
+        pinned.extend(self.state.pins().iter().cloned());
+
+        pinned
+    }
+
+    /// Removes the content with the given `cid` without being asked to, e.g. to simulate data loss.
+    pub fn lose(&self, cid: &str) {
+        self.state.content().remove(cid);
+        self.state.pins().remove(cid);
+    }
+
+    /// Replaces the content with the given `cid` by `data`, e.g. to simulate a publisher pinning bad content.
+    pub fn corrupt(&self, cid: &str, data: impl Into<Bytes>) {
+        self.state.content().insert(cid.to_owned(), data.into());
+    }
+
+    /// Fails the next `count` add requests, regardless of how many files they contain.
+    pub fn fail_next_adds(&self, count: usize) {
+        self.state.failures().adds = count;
+    }
+
+    /// Fails the next `count` cluster pin requests.
+    pub fn fail_next_pins(&self, count: usize) {
+        self.state.failures().pins = count;
+    }
+
+    /// Fails the next `count` cat requests.
+    pub fn fail_next_cats(&self, count: usize) {
+        self.state.failures().cats = count;
+    }
+
+    /// Fails the next `count` unpin requests.
+    pub fn fail_next_unpins(&self, count: usize) {
+        self.state.failures().unpins = count;
+    }
+}
+
+impl FakeIpfsState {
+    fn content(&self) -> std::sync::MutexGuard<'_, HashMap<String, Bytes>> {
+        self.content
+            .lock()
+            .expect("the lock should not be poisoned")
+    }
+
+    fn pins(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.pins.lock().expect("the lock should not be poisoned")
+    }
+
+    fn unpinned(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.unpinned
+            .lock()
+            .expect("the lock should not be poisoned")
+    }
+
+    fn failures(&self) -> std::sync::MutexGuard<'_, Failures> {
+        self.failures
+            .lock()
+            .expect("the lock should not be poisoned")
+    }
+
+    /// Returns whether the request should fail and counts it.
+    fn should_fail(&self, failures: impl FnOnce(&mut Failures) -> &mut usize) -> bool {
+        let mut guard = self.failures();
+        let remaining: &mut usize = failures(&mut guard);
+
+        if *remaining > 0 {
+            *remaining -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn add(&self, data: Bytes) -> Result<String, StatusCode> {
+        let cid: String = content_id(&data).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        self.content().insert(cid.clone(), data);
+        self.unpinned().remove(&cid);
+
+        Ok(cid)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ArgQuery {
+    arg: String,
+}
+
+/// Reads every file of the multipart form as a pair of its name and content.
+async fn read_files(mut multipart: Multipart) -> Result<Vec<(String, Bytes)>, StatusCode> {
+    let mut files: Vec<(String, Bytes)> = Vec::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    {
+        let name: String = field
+            .file_name()
+            .or_else(|| field.name())
+            .unwrap_or_default()
+            .to_owned();
+        let data: Bytes = field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?;
+
+        files.push((name, data));
+    }
+
+    Ok(files)
+}
+
+async fn cluster_add(
+    Extension(state): Extension<Arc<FakeIpfsState>>,
+    multipart: Multipart,
+) -> Result<String, StatusCode> {
+    if state.should_fail(|failures| &mut failures.adds) {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let mut body = String::new();
+    let files: Vec<(String, Bytes)> = read_files(multipart).await?;
+    let count: usize = files.len();
+
+    // Like the cluster, respond with one JSON object per file, separated by newlines.
+    for (i, (name, data)) in files.into_iter().enumerate() {
+        let response = AddResponse {
+            name,
+            size: data.len() as u64,
+            cid: state.add(data)?,
+            allocations: Vec::new(),
+        };
+
+        if i + 1 < count {
+            state.unpinned().insert(response.cid.clone());
+        }
+
+        body.push_str(
+            &serde_json::to_string(&response).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        );
+        body.push('\n');
+    }
+
+    Ok(body)
+}
+
+async fn cluster_pin(
+    Extension(state): Extension<Arc<FakeIpfsState>>,
+    Path(cid): Path<String>,
+) -> StatusCode {
+    if state.should_fail(|failures| &mut failures.pins) {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    if state.content().contains_key(&cid) {
+        state.unpinned().remove(&cid);
+    } else {
+        state.pins().insert(cid);
+    }
+
+    StatusCode::OK
+}
+
+async fn cluster_unpin(
+    Extension(state): Extension<Arc<FakeIpfsState>>,
+    Path(cid): Path<String>,
+) -> StatusCode {
+    if state.should_fail(|failures| &mut failures.unpins) {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    let removed_content: bool = state.content().remove(&cid).is_some();
+    let removed_pin: bool = state.pins().remove(&cid);
+
+    if removed_content || removed_pin {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn kubo_add(
+    Extension(state): Extension<Arc<FakeIpfsState>>,
+    multipart: Multipart,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if state.should_fail(|failures| &mut failures.adds) {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let (name, data): (String, Bytes) = read_files(multipart)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let size: usize = data.len();
+
+    Ok(Json(serde_json::json!({
+        "Name": name,
+        "Hash": state.add(data)?,
+        "Size": size.to_string(),
+    })))
+}
+
+async fn kubo_cat(
+    Extension(state): Extension<Arc<FakeIpfsState>>,
+    Query(query): Query<ArgQuery>,
+) -> Result<Bytes, StatusCode> {
+    if state.should_fail(|failures| &mut failures.cats) {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Kubo responds with an internal server error if the content can't be found.
+    state
+        .content()
+        .get(&query.arg)
+        .cloned()
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn kubo_pin_rm(
+    Extension(state): Extension<Arc<FakeIpfsState>>,
+    Query(query): Query<ArgQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if state.should_fail(|failures| &mut failures.unpins) {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    match state.content().remove(&query.arg) {
+        Some(_) => Ok(Json(serde_json::json!({ "Pins": [query.arg] }))),
+        None => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+async fn kubo_swarm_connect(Query(query): Query<ArgQuery>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "Strings": [format!("connect {} success", query.arg)],
+    }))
+}
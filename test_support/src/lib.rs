@@ -0,0 +1,64 @@
+//! Stand-ins for the IPFS cluster and the IOTA node, so that the anchor and resolver
+//! can be tested end-to-end without a network.
+
+mod documents;
+mod fake_ipfs;
+mod mock_iota_node;
+
+pub use documents::*;
+pub use fake_ipfs::*;
+pub use mock_iota_node::*;
+
+use std::path::{Path, PathBuf};
+
+use axum::Router;
+use didanchor::{AnchorConfig, ContentStoreConfig, LedgerConfig, RetentionPolicy, SecretSource};
+use iota_client::block::output::AliasId;
+use url::Url;
+
+/// Returns a config for an anchor that stores its content in `ipfs` and its local state in `dir`.
+///
+/// The ledger is meant to be passed to [`Anchor::from_parts`](didanchor::Anchor::from_parts),
+/// e.g. a [`MockIotaNode`].
+pub fn anchor_config(ipfs: &FakeIpfs, node: &MockIotaNode, dir: &Path) -> AnchorConfig {
+    AnchorConfig {
+        alias_id: AliasId::null(),
+        secret: SecretSource::default(),
+        mnemonic: None,
+        governor_address: None,
+        iota_endpoint: node.url().to_string(),
+        ipfs_node_public_addrs: Vec::new(),
+        ipfs_node_management_addrs: vec![ipfs.management_address()],
+        journal_path: dir.join("anchor_journal.jsonl"),
+        pin_gc_path: dir.join("anchor_pins.json"),
+        retention: RetentionPolicy::Forever,
+        content_store: ContentStoreConfig::IpfsCluster,
+        ledger: LedgerConfig::Memory,
+    }
+}
+
+/// Creates a new, empty directory in the system's temporary directory.
+pub fn temp_dir() -> anyhow::Result<PathBuf> {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("didanchor-test-{}", rand::random::<u64>()));
+
+    std::fs::create_dir_all(&path)?;
+
+    Ok(path)
+}
+
+/// Serves `router` on a random local port until the runtime shuts down.
+fn serve(router: Router) -> anyhow::Result<Url> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url: Url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+
+    let server = axum::Server::from_tcp(listener)?.serve(router.into_make_service());
+
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            log::error!("test server failed: {err}");
+        }
+    });
+
+    Ok(url)
+}
@@ -0,0 +1,464 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{extract::Path, http::StatusCode, routing::get, Extension, Json, Router};
+use didanchor::{AliasContent, AliasState, LedgerAnchor, LedgerReceipt};
+use iota_client::block::{
+    address::{Address, Ed25519Address},
+    dto::BlockDto,
+    input::{Input, UtxoInput},
+    output::{
+        dto::OutputDto,
+        unlock_condition::{
+            AddressUnlockCondition, GovernorAddressUnlockCondition,
+            StateControllerAddressUnlockCondition,
+        },
+        AliasId, AliasOutput, AliasOutputBuilder, BasicOutputBuilder, InputsCommitment, Output,
+        OutputId, UnlockCondition,
+    },
+    parent::Parents,
+    payload::{
+        transaction::{
+            RegularTransactionEssence, TransactionEssence, TransactionId, TransactionPayload,
+        },
+        Payload,
+    },
+    signature::{Ed25519Signature, Signature},
+    unlock::{ReferenceUnlock, SignatureUnlock, Unlock, Unlocks},
+    Block, BlockBuilder, BlockId,
+};
+use url::Url;
+
+/// A stand-in for an IOTA node that keeps the Alias Outputs of every alias in memory.
+///
+/// The Alias Outputs of each alias and the blocks of the transactions that created them are served through
+/// the indexer and core REST API routes that a [`Resolver`](didanchor::Resolver) and an
+/// [`AliasHistory`](didanchor::AliasHistory) use. Content is published through the node's
+/// [`LedgerAnchor`] implementation rather than by submitting blocks, since accepting those
+/// would require validating transactions and their signatures. The blocks it serves are made up
+/// accordingly and carry a signature that doesn't verify.
+#[derive(Clone)]
+pub struct MockIotaNode {
+    url: Url,
+    state: Arc<Mutex<LedgerState>>,
+}
+
+#[derive(Default)]
+struct LedgerState {
+    /// The outputs of every alias from the oldest to the current one.
+    aliases: HashMap<AliasId, Vec<OutputId>>,
+    outputs: HashMap<OutputId, PublishedOutput>,
+    /// The basic outputs that funded the transitions, by the Alias Output created with them.
+    funding: HashMap<OutputId, OutputId>,
+    milestone_index: u32,
+    failing_publishes: usize,
+    lost_confirmations: usize,
+}
+
+struct PublishedOutput {
+    alias_id: AliasId,
+    state_index: u32,
+    content: AliasContent,
+    /// The basic output that the transaction consumed besides the previous Alias Output.
+    funding: OutputId,
+    block_id: BlockId,
+    milestone_index: u32,
+    milestone_timestamp: u32,
+}
+
+/// Whether a node has pruned spent outputs and the blocks of their transactions.
+#[derive(Clone, Copy)]
+struct Pruned(bool);
+
+impl MockIotaNode {
+    /// The amount of every Alias Output, which is not checked against the storage deposit.
+    pub const OUTPUT_AMOUNT: u64 = 1_000_000;
+
+    /// Starts the node on a random local port.
+    pub fn start() -> anyhow::Result<Self> {
+        Self::serve(Arc::new(Mutex::new(LedgerState::default())), Pruned(false))
+    }
+
+    /// Starts another node on the same ledger that has pruned every spent output and every block,
+    /// so that older states of an alias can only be found through an archive node, e.g. this one.
+    pub fn start_pruned(&self) -> anyhow::Result<Self> {
+        Self::serve(Arc::clone(&self.state), Pruned(true))
+    }
+
+    fn serve(state: Arc<Mutex<LedgerState>>, pruned: Pruned) -> anyhow::Result<Self> {
+        let router = Router::new()
+            .route("/health", get(|| async { StatusCode::OK }))
+            .route(
+                "/api/indexer/v1/outputs/alias/:alias_id",
+                get(alias_output_id),
+            )
+            .route("/api/core/v2/outputs/:output_id", get(output))
+            .route(
+                "/api/core/v2/transactions/:transaction_id/included-block",
+                get(included_block),
+            )
+            .layer(Extension(Arc::clone(&state)))
+            .layer(Extension(pruned));
+
+        Ok(Self {
+            url: crate::serve(router)?,
+            state,
+        })
+    }
+
+    /// Returns the URL of the node's REST API.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Rejects the next `count` publishes, so that nothing is anchored.
+    pub fn fail_next_publishes(&self, count: usize) {
+        self.lock().failing_publishes = count;
+    }
+
+    /// Anchors the content of the next `count` publishes, but reports them as failed,
+    /// like a node that includes a block but fails to confirm it in time.
+    pub fn lose_next_confirmations(&self, count: usize) {
+        self.lock().lost_confirmations = count;
+    }
+
+    /// Moves the alias to a new output without changing its state, like a governance transition,
+    /// and returns the id of the new output.
+    pub fn transition_governance(&self, alias_id: AliasId) -> anyhow::Result<OutputId> {
+        let mut state = self.lock();
+
+        let (state_index, content): (u32, AliasContent) = state
+            .current_output(&alias_id)
+            .map(|(_, output)| (output.state_index, output.content.clone()))
+            .ok_or_else(|| anyhow::anyhow!("no output found for alias id {alias_id}"))?;
+
+        state.push_output(alias_id, state_index, content)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LedgerState> {
+        self.state.lock().expect("the lock should not be poisoned")
+    }
+}
+
+impl LedgerState {
+    fn current_output(&self, alias_id: &AliasId) -> Option<(OutputId, &PublishedOutput)> {
+        let output_id: OutputId = *self.aliases.get(alias_id)?.last()?;
+
+        Some((output_id, &self.outputs[&output_id]))
+    }
+
+    /// Confirms a new output of the alias in the next milestone, which creates the alias if `alias_id` is null.
+    fn push_output(
+        &mut self,
+        alias_id: AliasId,
+        state_index: u32,
+        content: AliasContent,
+    ) -> anyhow::Result<OutputId> {
+        let output_id: OutputId = OutputId::new(TransactionId::new(rand::random()), 0)?;
+        let funding: OutputId = OutputId::new(TransactionId::new(rand::random()), 0)?;
+
+        let alias_id: AliasId = if alias_id.is_null() {
+            AliasId::from(output_id)
+        } else {
+            alias_id
+        };
+
+        self.aliases.entry(alias_id).or_default().push(output_id);
+        self.funding.insert(funding, output_id);
+        self.milestone_index += 1;
+
+        let output = PublishedOutput {
+            alias_id,
+            state_index,
+            content,
+            funding,
+            block_id: BlockId::new(rand::random()),
+            milestone_index: self.milestone_index,
+            milestone_timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32,
+        };
+        self.outputs.insert(output_id, output);
+
+        Ok(output_id)
+    }
+
+    /// Returns the output of the same alias that spent the one with `output_id`, if any.
+    fn spent_by(&self, output_id: &OutputId) -> Option<(&OutputId, &PublishedOutput)> {
+        let outputs: &Vec<OutputId> = &self.aliases[&self.outputs[output_id].alias_id];
+        let position: usize = outputs.iter().position(|id| id == output_id)?;
+
+        outputs
+            .get(position + 1)
+            .map(|next| (next, &self.outputs[next]))
+    }
+}
+
+#[async_trait::async_trait]
+impl LedgerAnchor for MockIotaNode {
+    async fn publish_content(
+        &mut self,
+        alias_id: AliasId,
+        content: AliasContent,
+    ) -> anyhow::Result<LedgerReceipt> {
+        let mut state = self.lock();
+
+        if state.failing_publishes > 0 {
+            state.failing_publishes -= 1;
+            anyhow::bail!("the node rejected the block");
+        }
+
+        let state_index: u32 = if alias_id.is_null() {
+            0
+        } else {
+            match state.current_output(&alias_id) {
+                Some((_, output)) => output.state_index + 1,
+                None => anyhow::bail!("no output found for alias id {alias_id}"),
+            }
+        };
+
+        let output_id: OutputId = state.push_output(alias_id, state_index, content)?;
+        let (alias_id, block_id): (AliasId, BlockId) = {
+            let output: &PublishedOutput = &state.outputs[&output_id];
+            (output.alias_id, output.block_id)
+        };
+
+        if state.lost_confirmations > 0 {
+            state.lost_confirmations -= 1;
+            anyhow::bail!("the block was not confirmed in time");
+        }
+
+        Ok(LedgerReceipt {
+            alias_id,
+            state_index,
+            block_id: Some(block_id),
+            transaction_id: Some(*output_id.transaction_id()),
+            output_id: Some(output_id),
+            storage_deposit: Some(Self::OUTPUT_AMOUNT),
+        })
+    }
+
+    async fn read_content(&self, alias_id: AliasId) -> anyhow::Result<Option<AliasContent>> {
+        Ok(self
+            .lock()
+            .current_output(&alias_id)
+            .map(|(_, output)| output.content.clone()))
+    }
+
+    async fn read_history(&self, alias_id: AliasId) -> anyhow::Result<Vec<AliasState>> {
+        let state = self.lock();
+
+        let mut history: Vec<AliasState> = Vec::new();
+
+        for output_id in state.aliases.get(&alias_id).into_iter().flatten() {
+            let output: &PublishedOutput = &state.outputs[output_id];
+
+            // Governance transitions create a new output, but not a new state.
+            if history.last().map(|last| last.state_index) == Some(output.state_index) {
+                continue;
+            }
+
+            history.push(AliasState {
+                state_index: output.state_index,
+                content: output.content.clone(),
+            });
+        }
+
+        Ok(history)
+    }
+
+    async fn state_index(&self, alias_id: AliasId) -> anyhow::Result<Option<u32>> {
+        Ok(self
+            .lock()
+            .current_output(&alias_id)
+            .map(|(_, output)| output.state_index))
+    }
+}
+
+fn address() -> Address {
+    Address::Ed25519(Ed25519Address::new([0; 32]))
+}
+
+fn alias_output(output: &PublishedOutput) -> anyhow::Result<AliasOutput> {
+    Ok(
+        AliasOutputBuilder::new_with_amount(MockIotaNode::OUTPUT_AMOUNT, output.alias_id)?
+            .with_state_index(output.state_index)
+            .with_foundry_counter(0)
+            .with_state_metadata(output.content.to_state_metadata()?)
+            .add_unlock_condition(UnlockCondition::StateControllerAddress(
+                StateControllerAddressUnlockCondition::new(address()),
+            ))
+            .add_unlock_condition(UnlockCondition::GovernorAddress(
+                GovernorAddressUnlockCondition::new(address()),
+            ))
+            .finish()?,
+    )
+}
+
+fn funding_output() -> anyhow::Result<Output> {
+    Ok(Output::Basic(
+        BasicOutputBuilder::new_with_amount(MockIotaNode::OUTPUT_AMOUNT)?
+            .add_unlock_condition(UnlockCondition::Address(AddressUnlockCondition::new(
+                address(),
+            )))
+            .finish()?,
+    ))
+}
+
+/// Builds a block with the transaction that created the output with `output_id`.
+///
+/// It consumes the funding output first and the previous output of the alias, if any, second.
+fn transaction_block(state: &LedgerState, output_id: &OutputId) -> anyhow::Result<Block> {
+    let output: &PublishedOutput = &state.outputs[output_id];
+    let outputs: &Vec<OutputId> = &state.aliases[&output.alias_id];
+    let position: usize = outputs
+        .iter()
+        .position(|id| id == output_id)
+        .expect("every output is listed for its alias");
+
+    let mut inputs: Vec<(OutputId, Output)> = vec![(output.funding, funding_output()?)];
+    if let Some(previous) = position.checked_sub(1).map(|position| outputs[position]) {
+        inputs.push((
+            previous,
+            Output::Alias(alias_output(&state.outputs[&previous])?),
+        ));
+    }
+
+    let essence = RegularTransactionEssence::builder(
+        0,
+        InputsCommitment::new(inputs.iter().map(|(_, output)| output)),
+    )
+    .with_inputs(
+        inputs
+            .iter()
+            .map(|(output_id, _)| Input::Utxo(UtxoInput::from(*output_id)))
+            .collect(),
+    )
+    .with_outputs(vec![Output::Alias(alias_output(output)?)])
+    .finish()?;
+
+    let mut unlocks: Vec<Unlock> = vec![Unlock::Signature(SignatureUnlock::new(
+        Signature::Ed25519(Ed25519Signature::new([0; 32], [0; 64])),
+    ))];
+    for _ in 1..inputs.len() {
+        unlocks.push(Unlock::Reference(ReferenceUnlock::new(0)?));
+    }
+
+    let payload =
+        TransactionPayload::new(TransactionEssence::Regular(essence), Unlocks::new(unlocks)?)?;
+
+    Ok(
+        BlockBuilder::<u64>::new(Parents::new(vec![BlockId::new(rand::random())])?)
+            .with_payload(Payload::Transaction(Box::new(payload)))
+            .finish()?,
+    )
+}
+
+async fn alias_output_id(
+    Extension(state): Extension<Arc<Mutex<LedgerState>>>,
+    Path(alias_id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let alias_id: AliasId = AliasId::from_str(&alias_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let state = state.lock().expect("the lock should not be poisoned");
+
+    let (output_id, _) = state
+        .current_output(&alias_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(serde_json::json!({
+        "ledgerIndex": state.milestone_index,
+        "cursor": null,
+        "items": [output_id.to_string()],
+    })))
+}
+
+async fn output(
+    Extension(state): Extension<Arc<Mutex<LedgerState>>>,
+    Extension(Pruned(pruned)): Extension<Pruned>,
+    Path(output_id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let output_id: OutputId =
+        OutputId::from_str(&output_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let state = state.lock().expect("the lock should not be poisoned");
+
+    // Funding outputs are only known once spent.
+    if let Some(spent_by_id) = state.funding.get(&output_id) {
+        if pruned {
+            return Err(StatusCode::NOT_FOUND);
+        }
+
+        let spent_by: &PublishedOutput = &state.outputs[spent_by_id];
+        let funding_output: Output =
+            funding_output().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return Ok(Json(serde_json::json!({
+            "metadata": {
+                "blockId": BlockId::new([0; 32]).to_string(),
+                "transactionId": output_id.transaction_id().to_string(),
+                "outputIndex": output_id.index(),
+                "isSpent": true,
+                "milestoneIndexSpent": spent_by.milestone_index,
+                "milestoneTimestampSpent": spent_by.milestone_timestamp,
+                "transactionIdSpent": spent_by_id.transaction_id().to_string(),
+                "milestoneIndexBooked": 0,
+                "milestoneTimestampBooked": 0,
+                "ledgerIndex": state.milestone_index,
+            },
+            "output": OutputDto::from(&funding_output),
+        })));
+    }
+
+    let output: &PublishedOutput = state.outputs.get(&output_id).ok_or(StatusCode::NOT_FOUND)?;
+    let alias_output: AliasOutput =
+        alias_output(output).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Every output but the current one of its alias was spent by the next transition.
+    let spent_by: Option<(&OutputId, &PublishedOutput)> = state.spent_by(&output_id);
+
+    if pruned && spent_by.is_some() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(serde_json::json!({
+        "metadata": {
+            "blockId": output.block_id.to_string(),
+            "transactionId": output_id.transaction_id().to_string(),
+            "outputIndex": output_id.index(),
+            "isSpent": spent_by.is_some(),
+            "milestoneIndexSpent": spent_by.map(|(_, next)| next.milestone_index),
+            "milestoneTimestampSpent": spent_by.map(|(_, next)| next.milestone_timestamp),
+            "transactionIdSpent": spent_by.map(|(next_id, _)| next_id.transaction_id().to_string()),
+            "milestoneIndexBooked": output.milestone_index,
+            "milestoneTimestampBooked": output.milestone_timestamp,
+            "ledgerIndex": state.milestone_index,
+        },
+        "output": OutputDto::from(&Output::Alias(alias_output)),
+    })))
+}
+
+async fn included_block(
+    Extension(state): Extension<Arc<Mutex<LedgerState>>>,
+    Extension(Pruned(pruned)): Extension<Pruned>,
+    Path(transaction_id): Path<String>,
+) -> Result<Json<BlockDto>, StatusCode> {
+    if pruned {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let transaction_id: TransactionId =
+        TransactionId::from_str(&transaction_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let output_id: OutputId =
+        OutputId::new(transaction_id, 0).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let state = state.lock().expect("the lock should not be poisoned");
+
+    if !state.outputs.contains_key(&output_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let block: Block =
+        transaction_block(&state, &output_id).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(BlockDto::from(&block)))
+}
@@ -0,0 +1,553 @@
+use didanchor::{
+    AliasContentVersion, AliasHistory, Anchor, AnchorConfig, AuditFinding, Auditor,
+    HistoricalAliasState, KuboStore, LedgerAnchor, Mirror, ResolutionError, Resolver, RootCheck,
+};
+use identity_did::did::CoreDID;
+use iota_client::{block::output::AliasId, Client};
+use ipfs_cluster::IpfsCluster;
+use test_support::{
+    anchor_config, anchored_did, new_document, temp_dir, update_document, FakeIpfs, MockIotaNode,
+};
+
+struct Environment {
+    ipfs: FakeIpfs,
+    node: MockIotaNode,
+    config: AnchorConfig,
+}
+
+impl Environment {
+    fn start() -> Self {
+        let ipfs = FakeIpfs::start().unwrap();
+        let node = MockIotaNode::start().unwrap();
+        let config = anchor_config(&ipfs, &node, &temp_dir().unwrap());
+
+        Self { ipfs, node, config }
+    }
+
+    /// Creates an anchor, which continues where the previous one stopped if given its `alias_id`.
+    async fn anchor(&self, alias_id: AliasId) -> Anchor {
+        let mut config: AnchorConfig = self.config.clone();
+        config.alias_id = alias_id;
+
+        let storage = config.chain_storage().await.unwrap();
+
+        Anchor::from_parts(config, storage, self.node.clone())
+            .await
+            .unwrap()
+    }
+
+    fn resolver(&self) -> Resolver {
+        Resolver::new(self.node.url().as_str(), self.ipfs.url().as_str()).unwrap()
+    }
+
+    /// Walks the history of aliases through `node`, which may be a pruned view of the environment's node.
+    fn history(node: &MockIotaNode) -> AliasHistory {
+        AliasHistory::new(
+            Client::builder()
+                .with_primary_node(node.url().as_str(), None)
+                .unwrap()
+                .finish()
+                .unwrap(),
+        )
+    }
+
+    /// Creates a mirror of the published content that pins it on `cluster`.
+    fn mirror(&self, cluster: &FakeIpfs) -> Mirror {
+        Mirror::new(
+            self.node.clone(),
+            KuboStore::new(self.ipfs.url().clone()).unwrap(),
+            IpfsCluster::new(vec![cluster.url().clone()]).unwrap(),
+        )
+    }
+}
+
+#[tokio::test]
+async fn test_anchor_commit_and_resolve() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let (keypair, doc1) = new_document();
+    let (_, doc2) = new_document();
+
+    anchor.update_document(doc1.clone()).await.unwrap();
+    anchor.update_document(doc2.clone()).await.unwrap();
+
+    let receipt = anchor.commit_changes().await.unwrap();
+    let alias_id = receipt.alias_id();
+    assert!(anchor.pending_changes().is_empty());
+    assert_eq!(env.node.state_index(alias_id).await.unwrap(), Some(0));
+
+    assert_eq!(receipt.ledger.state_index, 0);
+    assert_eq!(
+        receipt.ledger.storage_deposit,
+        Some(MockIotaNode::OUTPUT_AMOUNT)
+    );
+    assert!(receipt.ledger.block_id.is_some());
+    assert!(receipt.previous_index_cid.is_none());
+    assert_eq!(receipt.chains.len(), 2);
+    assert!(receipt.chain(doc1.document.id()).is_some());
+
+    for chain in receipt.chains.iter() {
+        assert!(env.ipfs.contains(&chain.cid));
+    }
+
+    let resolver = env.resolver();
+    let dids: Vec<CoreDID> = vec![anchored_did(alias_id, &doc1), anchored_did(alias_id, &doc2)];
+
+    for result in resolver.resolve_many(&dids).await {
+        assert!(result.unwrap().is_some());
+    }
+
+    // An unknown DID of a known alias is not an error.
+    let (_, unknown) = new_document();
+    assert!(resolver
+        .resolve(&anchored_did(alias_id, &unknown))
+        .await
+        .unwrap()
+        .is_none());
+
+    let doc1 = update_document(&keypair, doc1, |doc| {
+        doc.properties_mut().insert("updated".into(), true.into());
+    });
+
+    anchor.update_document(doc1.clone()).await.unwrap();
+    let next_receipt = anchor.commit_changes().await.unwrap();
+    assert_eq!(next_receipt.alias_id(), alias_id);
+    assert_eq!(next_receipt.ledger.state_index, 1);
+    assert_eq!(env.node.state_index(alias_id).await.unwrap(), Some(1));
+
+    assert_eq!(next_receipt.previous_index_cid, Some(receipt.index_cid));
+    assert_eq!(next_receipt.dids(), vec![doc1.document.id().clone()]);
+
+    let document = resolver
+        .resolve(&anchored_did(alias_id, &doc1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(document.properties().get("updated"), Some(&true.into()));
+
+    // The unchanged DID still resolves against the new merkle root.
+    assert!(resolver
+        .resolve(&anchored_did(alias_id, &doc2))
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_rejected_publish_keeps_changes_pending() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let (_, doc) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+
+    env.node.fail_next_publishes(1);
+    assert!(anchor.commit_changes().await.is_err());
+
+    // The content of the failed commit is unpinned again.
+    assert_eq!(anchor.pending_changes().len(), 1);
+    assert!(env.ipfs.pinned().is_empty());
+
+    let alias_id = anchor.commit_changes().await.unwrap().alias_id();
+
+    assert!(env
+        .resolver()
+        .resolve(&anchored_did(alias_id, &doc))
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_failed_upload_keeps_changes_pending() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let (_, doc) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+
+    env.ipfs.fail_next_adds(1);
+    assert!(anchor.commit_changes().await.is_err());
+
+    assert_eq!(anchor.pending_changes().len(), 1);
+    assert!(anchor.alias_id().is_null());
+
+    let alias_id = anchor.commit_changes().await.unwrap().alias_id();
+    assert_eq!(env.node.state_index(alias_id).await.unwrap(), Some(0));
+}
+
+#[tokio::test]
+async fn test_failed_pin_rolls_back_the_whole_batch() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let (_, doc1) = new_document();
+    let (_, doc2) = new_document();
+    anchor.update_document(doc1).await.unwrap();
+    anchor.update_document(doc2).await.unwrap();
+
+    // The add of the batch succeeds, but pinning one of its files fails.
+    env.ipfs.fail_next_pins(1);
+    assert!(anchor.commit_changes().await.is_err());
+
+    // The content that was added before the failure is unpinned again.
+    assert!(env.ipfs.pinned().is_empty());
+    assert_eq!(anchor.pending_changes().len(), 2);
+
+    anchor.commit_changes().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lost_confirmation_is_recognized_as_anchored() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let (keypair, doc) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+    let alias_id = anchor.commit_changes().await.unwrap().alias_id();
+
+    let doc = update_document(&keypair, doc, |doc| {
+        doc.properties_mut().insert("updated".into(), true.into());
+    });
+    anchor.update_document(doc).await.unwrap();
+
+    env.node.lose_next_confirmations(1);
+    let receipt = anchor.commit_changes().await.unwrap();
+    assert_eq!(receipt.alias_id(), alias_id);
+
+    // Only the state is known of an output that was found to be included after all.
+    assert_eq!(receipt.ledger.state_index, 1);
+    assert!(receipt.ledger.block_id.is_none());
+
+    assert!(anchor.pending_changes().is_empty());
+    assert_eq!(env.node.state_index(alias_id).await.unwrap(), Some(1));
+}
+
+#[tokio::test]
+async fn test_restarted_anchor_replays_pending_changes() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let (keypair, doc) = new_document();
+    let (_, untouched) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+    anchor.update_document(untouched).await.unwrap();
+    let alias_id = anchor.commit_changes().await.unwrap().alias_id();
+
+    let doc = update_document(&keypair, doc, |doc| {
+        doc.properties_mut().insert("updated".into(), true.into());
+    });
+    anchor.update_document(doc.clone()).await.unwrap();
+
+    drop(anchor);
+
+    let mut anchor = env.anchor(alias_id).await;
+    assert_eq!(anchor.pending_changes().len(), 1);
+    assert_eq!(anchor.commit_changes().await.unwrap().alias_id(), alias_id);
+
+    // The new root also commits to the DID that wasn't touched since the restart.
+    let auditor = Auditor::new(env.node.url().as_str(), env.ipfs.url().as_str()).unwrap();
+    let report = auditor.audit(alias_id, |_| ()).await.unwrap();
+    assert_eq!(report.dids, 2);
+    assert_eq!(report.root, RootCheck::Reproduced);
+
+    let document = env
+        .resolver()
+        .resolve(&anchored_did(alias_id, &doc))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(document.properties().get("updated"), Some(&true.into()));
+}
+
+#[tokio::test]
+async fn test_resolution_failures() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+    let resolver = env.resolver();
+
+    let (_, doc) = new_document();
+    let (_, corrupted) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+    anchor.update_document(corrupted.clone()).await.unwrap();
+    let receipt = anchor.commit_changes().await.unwrap();
+    let alias_id = receipt.alias_id();
+
+    let unknown_did = anchored_did(AliasId::new(rand::random()), &doc);
+    let err = resolver.resolve(&unknown_did).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ResolutionError>(),
+        Some(ResolutionError::AliasNotFound(_))
+    ));
+
+    // A failure shared by several DIDs keeps its type for each of them.
+    for result in resolver
+        .resolve_many(&[unknown_did.clone(), unknown_did])
+        .await
+    {
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<ResolutionError>(),
+            Some(ResolutionError::AliasNotFound(_))
+        ));
+    }
+
+    // Content that can't be fetched fails the resolution, but not the next one.
+    env.ipfs.fail_next_cats(1);
+    assert!(resolver
+        .resolve(&anchored_did(alias_id, &doc))
+        .await
+        .is_err());
+    assert!(resolver
+        .resolve(&anchored_did(alias_id, &doc))
+        .await
+        .unwrap()
+        .is_some());
+
+    // Content that is not a chain of custody is rejected instead of crashing the resolver.
+    let chain = receipt.chain(corrupted.document.id()).unwrap();
+    env.ipfs.corrupt(&chain.cid, b"garbage".to_vec());
+    let err = resolver
+        .resolve(&anchored_did(alias_id, &corrupted))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ResolutionError>(),
+        Some(ResolutionError::InvalidChainOfCustody(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_mirror_follows_the_published_content() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+    let cluster = FakeIpfs::start().unwrap();
+    let mut mirror = env.mirror(&cluster);
+
+    let (keypair, doc) = new_document();
+    let (_, unchanged) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+    anchor.update_document(unchanged.clone()).await.unwrap();
+    let receipt = anchor.commit_changes().await.unwrap();
+    let alias_id = receipt.alias_id();
+
+    let report = mirror.sync(alias_id).await.unwrap().unwrap();
+    assert_eq!(report.state_index, 0);
+    assert_eq!(report.index_cid, receipt.index_cid);
+    assert!(report.rejected.is_empty());
+    assert!(report.unpinned.is_empty());
+
+    assert!(cluster.contains(&receipt.index_cid));
+    for chain in receipt.chains.iter() {
+        assert!(cluster.contains(&chain.cid));
+    }
+
+    // An index that was already mirrored is not mirrored again.
+    assert!(mirror.sync(alias_id).await.unwrap().is_none());
+
+    let doc = update_document(&keypair, doc, |doc| {
+        doc.properties_mut().insert("updated".into(), true.into());
+    });
+    anchor.update_document(doc.clone()).await.unwrap();
+    let next_receipt = anchor.commit_changes().await.unwrap();
+
+    let report = mirror.sync(alias_id).await.unwrap().unwrap();
+    assert_eq!(report.state_index, 1);
+    assert!(report.pinned.contains(&next_receipt.index_cid));
+    // The DID that was not updated is still committed to by the new root.
+    assert!(report.rejected.is_empty());
+
+    // The superseded index and chain of custody are unpinned.
+    let superseded = receipt.chain(doc.document.id()).unwrap();
+    assert!(report.unpinned.contains(&receipt.index_cid));
+    assert!(!cluster.contains(&receipt.index_cid));
+    assert!(!cluster.contains(&superseded.cid));
+    assert!(cluster.contains(&next_receipt.chain(doc.document.id()).unwrap().cid));
+}
+
+#[tokio::test]
+async fn test_restarted_mirror_unpins_content_superseded_in_between() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+    let cluster = FakeIpfs::start().unwrap();
+    let state_path = temp_dir().unwrap().join("mirror.json");
+
+    let (keypair, doc) = new_document();
+    anchor.update_document(doc.clone()).await.unwrap();
+    let receipt = anchor.commit_changes().await.unwrap();
+    let alias_id = receipt.alias_id();
+
+    let mut mirror = env
+        .mirror(&cluster)
+        .with_state_path(&state_path)
+        .await
+        .unwrap();
+    mirror.sync(alias_id).await.unwrap().unwrap();
+    drop(mirror);
+
+    let doc = update_document(&keypair, doc, |doc| {
+        doc.properties_mut().insert("updated".into(), true.into());
+    });
+    anchor.update_document(doc).await.unwrap();
+    anchor.commit_changes().await.unwrap();
+
+    let mut mirror = env
+        .mirror(&cluster)
+        .with_state_path(&state_path)
+        .await
+        .unwrap();
+    let report = mirror.sync(alias_id).await.unwrap().unwrap();
+    assert_eq!(report.state_index, 1);
+    assert!(report.unpinned.contains(&receipt.index_cid));
+    assert!(!cluster.contains(&receipt.index_cid));
+    assert!(!cluster.contains(&receipt.chains[0].cid));
+}
+
+#[tokio::test]
+async fn test_mirror_rejects_unavailable_chains() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+    let cluster = FakeIpfs::start().unwrap();
+    let mut mirror = env.mirror(&cluster);
+
+    let (_, doc1) = new_document();
+    let (_, doc2) = new_document();
+    anchor.update_document(doc1.clone()).await.unwrap();
+    anchor.update_document(doc2.clone()).await.unwrap();
+    let receipt = anchor.commit_changes().await.unwrap();
+
+    let lost = receipt.chain(doc2.document.id()).unwrap();
+    env.ipfs.lose(&lost.cid);
+
+    let report = mirror.sync(receipt.alias_id()).await.unwrap().unwrap();
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(&report.rejected[0].did, doc2.document.id());
+
+    assert!(!cluster.contains(&lost.cid));
+    assert!(cluster.contains(&receipt.chain(doc1.document.id()).unwrap().cid));
+}
+
+#[tokio::test]
+async fn test_audit_reports_missing_chains() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+    let auditor = Auditor::new(env.node.url().as_str(), env.ipfs.url().as_str()).unwrap();
+
+    let (_, doc1) = new_document();
+    let (_, doc2) = new_document();
+    anchor.update_document(doc1).await.unwrap();
+    anchor.update_document(doc2.clone()).await.unwrap();
+    let receipt = anchor.commit_changes().await.unwrap();
+    let alias_id = receipt.alias_id();
+
+    let mut progress = Vec::new();
+    let report = auditor
+        .audit(alias_id, |update| progress.push(update.checked))
+        .await
+        .unwrap();
+    assert!(report.is_clean());
+    assert_eq!(report.dids, 2);
+    assert_eq!(report.verified, 2);
+    assert_eq!(report.merkle_root, receipt.merkle_root);
+    assert_eq!(progress, vec![0, 1, 2]);
+
+    let lost = receipt.chain(doc2.document.id()).unwrap();
+    env.ipfs.lose(&lost.cid);
+
+    let report = auditor.audit(alias_id, |_| ()).await.unwrap();
+    assert!(!report.is_clean());
+    assert_eq!(report.verified, 1);
+    assert_eq!(report.root, RootCheck::Incomplete);
+    assert!(matches!(
+        report.findings.as_slice(),
+        [AuditFinding::MissingCid { did, cid, .. }] if did == doc2.document.id() && cid == &lost.cid
+    ));
+}
+
+#[tokio::test]
+async fn test_audit_covers_dids_unchanged_by_the_latest_commit() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+    let auditor = Auditor::new(env.node.url().as_str(), env.ipfs.url().as_str()).unwrap();
+
+    let (keypair, doc1) = new_document();
+    let (_, doc2) = new_document();
+    anchor.update_document(doc1.clone()).await.unwrap();
+    anchor.update_document(doc2).await.unwrap();
+    anchor.commit_changes().await.unwrap();
+
+    let doc1 = update_document(&keypair, doc1, |doc| {
+        doc.properties_mut().insert("updated".into(), true.into());
+    });
+    anchor.update_document(doc1).await.unwrap();
+    let receipt = anchor.commit_changes().await.unwrap();
+
+    let report = auditor.audit(receipt.alias_id(), |_| ()).await.unwrap();
+    assert!(report.is_clean(), "{:?}", report.findings);
+    assert_eq!(report.state_index, 1);
+    assert_eq!(report.verified, 2);
+    assert_eq!(report.root, RootCheck::Reproduced);
+}
+
+#[tokio::test]
+async fn test_alias_history_walks_back_through_governance_transitions() {
+    let env = Environment::start();
+    let mut anchor = env.anchor(AliasId::null()).await;
+
+    let mut receipts = Vec::new();
+
+    for state_index in 0..3 {
+        let (_, doc) = new_document();
+        anchor.update_document(doc).await.unwrap();
+        receipts.push(anchor.commit_changes().await.unwrap());
+
+        if state_index == 0 {
+            env.node
+                .transition_governance(receipts[0].alias_id())
+                .unwrap();
+        }
+    }
+    let alias_id = receipts[0].alias_id();
+
+    let history: Vec<HistoricalAliasState> = Environment::history(&env.node)
+        .states(alias_id)
+        .await
+        .unwrap();
+
+    // The governance transition neither adds a state nor changes when the first one was confirmed.
+    assert_eq!(history.len(), 3);
+    for (state, receipt) in history.iter().zip(receipts.iter()) {
+        assert_eq!(state.state_index, receipt.ledger.state_index);
+        assert_eq!(state.content.index_cid, receipt.index_cid);
+        assert_eq!(Some(state.output_id), receipt.ledger.output_id);
+        assert_eq!(state.version, AliasContentVersion::LATEST);
+    }
+    assert_eq!(
+        history
+            .iter()
+            .map(|state| state.milestone_index)
+            .collect::<Vec<u32>>(),
+        vec![1, 3, 4]
+    );
+
+    let since: Vec<HistoricalAliasState> = Environment::history(&env.node)
+        .states_since(alias_id, 1)
+        .await
+        .unwrap();
+    assert_eq!(since, history[1..]);
+
+    // A pruned node only knows the current state, so older ones are asked from the archive node.
+    let pruned: MockIotaNode = env.node.start_pruned().unwrap();
+    assert!(Environment::history(&pruned)
+        .states(alias_id)
+        .await
+        .is_err());
+
+    let archived: Vec<HistoricalAliasState> = Environment::history(&pruned)
+        .with_archive_node(env.node.url().as_str())
+        .unwrap()
+        .states(alias_id)
+        .await
+        .unwrap();
+    assert_eq!(archived, history);
+}
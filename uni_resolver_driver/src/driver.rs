@@ -0,0 +1,188 @@
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use didanchor::{ResolutionError, Resolver};
+use identity_did::{did::CoreDID, document::CoreDocument};
+
+use crate::resolution_result::{
+    ResolutionErrorCode, ResolutionResult, DID_JSON_CONTENT_TYPE, DID_LD_JSON_CONTENT_TYPE,
+    RESOLUTION_RESULT_CONTENT_TYPE,
+};
+
+/// Handles `GET /1.0/identifiers/{did}`.
+///
+/// <https://github.com/decentralized-identity/universal-resolver/blob/main/docs/driver-development.md>
+pub async fn resolve(
+    Path(did): Path<String>,
+    headers: HeaderMap,
+    Extension(resolver): Extension<Arc<Resolver>>,
+) -> Response {
+    let time = Instant::now();
+
+    let representation: Representation =
+        match Representation::from_accept(headers.get(header::ACCEPT)) {
+            Some(representation) => representation,
+            None => {
+                return error_response(
+                    ResolutionErrorCode::RepresentationNotSupported,
+                    "the requested representation is not supported".to_owned(),
+                    time,
+                )
+            }
+        };
+
+    let did: CoreDID = match CoreDID::parse(&did) {
+        Ok(did) => did,
+        Err(err) => return error_response(ResolutionErrorCode::InvalidDid, err.to_string(), time),
+    };
+
+    log::debug!("resolving {did}");
+
+    match resolver.resolve(&did).await {
+        Ok(Some(document)) => success_response(document, representation, time),
+        Ok(None) => error_response(
+            ResolutionErrorCode::NotFound,
+            format!("{did} is not published by its alias"),
+            time,
+        ),
+        Err(err) => {
+            let code: ResolutionErrorCode = match err.downcast_ref::<ResolutionError>() {
+                Some(ResolutionError::InvalidDid(_)) => ResolutionErrorCode::InvalidDid,
+                Some(ResolutionError::AliasNotFound(_)) => ResolutionErrorCode::NotFound,
+                Some(ResolutionError::InvalidChainOfCustody(_)) | None => {
+                    ResolutionErrorCode::InternalError
+                }
+            };
+
+            if code == ResolutionErrorCode::InternalError {
+                log::error!("failed to resolve {did}: {err:#}");
+            }
+
+            error_response(code, format!("{err:#}"), time)
+        }
+    }
+}
+
+fn success_response(
+    document: CoreDocument,
+    representation: Representation,
+    time: Instant,
+) -> Response {
+    match representation {
+        Representation::ResolutionResult => json_response(
+            StatusCode::OK,
+            RESOLUTION_RESULT_CONTENT_TYPE,
+            &ResolutionResult::success(
+                document,
+                DID_LD_JSON_CONTENT_TYPE,
+                time.elapsed().as_millis(),
+            ),
+        ),
+        Representation::DidLdJson => {
+            json_response(StatusCode::OK, DID_LD_JSON_CONTENT_TYPE, &document)
+        }
+        Representation::DidJson => json_response(StatusCode::OK, DID_JSON_CONTENT_TYPE, &document),
+    }
+}
+
+fn error_response(code: ResolutionErrorCode, message: String, time: Instant) -> Response {
+    json_response(
+        status_code(code),
+        RESOLUTION_RESULT_CONTENT_TYPE,
+        &ResolutionResult::error(code, message, time.elapsed().as_millis()),
+    )
+}
+
+fn json_response(
+    status: StatusCode,
+    content_type: &'static str,
+    body: &impl serde::Serialize,
+) -> Response {
+    match serde_json::to_vec(body) {
+        Ok(body) => (
+            status,
+            [(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+            body,
+        )
+            .into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+fn status_code(code: ResolutionErrorCode) -> StatusCode {
+    match code {
+        ResolutionErrorCode::InvalidDid => StatusCode::BAD_REQUEST,
+        ResolutionErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ResolutionErrorCode::RepresentationNotSupported => StatusCode::NOT_ACCEPTABLE,
+        ResolutionErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The representations of a resolved DID that a client can request via the `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Representation {
+    ResolutionResult,
+    DidLdJson,
+    DidJson,
+}
+
+impl Representation {
+    /// Returns the first supported representation listed in the `Accept` header,
+    /// or `None` if none of them are supported.
+    fn from_accept(accept: Option<&HeaderValue>) -> Option<Self> {
+        let accept: &str = match accept.and_then(|accept| accept.to_str().ok()) {
+            Some(accept) if !accept.trim().is_empty() => accept,
+            _ => return Some(Representation::ResolutionResult),
+        };
+
+        accept.split(',').find_map(|media_range| {
+            let media_type: &str = media_range.split(';').next().unwrap_or_default().trim();
+
+            match media_type {
+                "application/ld+json" | "application/json" | "application/*" | "*/*" => {
+                    Some(Representation::ResolutionResult)
+                }
+                DID_LD_JSON_CONTENT_TYPE => Some(Representation::DidLdJson),
+                DID_JSON_CONTENT_TYPE => Some(Representation::DidJson),
+                _ => None,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::Representation;
+
+    fn representation(accept: &'static str) -> Option<Representation> {
+        Representation::from_accept(Some(&HeaderValue::from_static(accept)))
+    }
+
+    #[test]
+    fn test_representation_from_accept() {
+        assert_eq!(
+            Representation::from_accept(None),
+            Some(Representation::ResolutionResult)
+        );
+        assert_eq!(
+            representation(r#"application/ld+json;profile="https://w3id.org/did-resolution""#),
+            Some(Representation::ResolutionResult)
+        );
+        assert_eq!(
+            representation("application/did+ld+json"),
+            Some(Representation::DidLdJson)
+        );
+        assert_eq!(
+            representation("text/html, application/did+json;q=0.9"),
+            Some(Representation::DidJson)
+        );
+        assert_eq!(representation("text/html"), None);
+    }
+}
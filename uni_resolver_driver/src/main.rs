@@ -0,0 +1,46 @@
+mod driver;
+mod resolution_result;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Context;
+use axum::{routing::get, Extension, Router};
+use didanchor::Resolver;
+
+/// The endpoint of the IOTA node used to resolve Alias Outputs.
+const IOTA_ENDPOINT_VAR: &str = "IOTA_ENDPOINT";
+/// The RPC API endpoint of the IPFS node used to fetch indices and chains of custody.
+const IPFS_ENDPOINT_VAR: &str = "IPFS_ENDPOINT";
+/// The address the driver listens on.
+const LISTEN_ADDR_VAR: &str = "LISTEN_ADDR";
+
+const DEFAULT_IPFS_ENDPOINT: &str = "http://127.0.0.1:5001";
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:8080";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+
+    let iota_endpoint: String = std::env::var(IOTA_ENDPOINT_VAR)
+        .with_context(|| format!("the {IOTA_ENDPOINT_VAR} environment variable must be set"))?;
+    let ipfs_endpoint: String =
+        std::env::var(IPFS_ENDPOINT_VAR).unwrap_or_else(|_| DEFAULT_IPFS_ENDPOINT.to_owned());
+    let listen_addr: SocketAddr = std::env::var(LISTEN_ADDR_VAR)
+        .unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_owned())
+        .parse()
+        .with_context(|| format!("{LISTEN_ADDR_VAR} must be a socket address"))?;
+
+    let resolver = Resolver::new(&iota_endpoint, &ipfs_endpoint)?;
+
+    let app = Router::new()
+        .route("/1.0/identifiers/:did", get(driver::resolve))
+        .layer(Extension(Arc::new(resolver)));
+
+    log::info!("listening on {listen_addr}");
+
+    axum::Server::bind(&listen_addr)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+}
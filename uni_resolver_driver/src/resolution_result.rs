@@ -0,0 +1,79 @@
+use identity_did::document::CoreDocument;
+
+/// The content type of a DID resolution result.
+pub const RESOLUTION_RESULT_CONTENT_TYPE: &str =
+    r#"application/ld+json;profile="https://w3id.org/did-resolution""#;
+/// The content type of a DID document in JSON-LD.
+pub const DID_LD_JSON_CONTENT_TYPE: &str = "application/did+ld+json";
+/// The content type of a DID document in plain JSON.
+pub const DID_JSON_CONTENT_TYPE: &str = "application/did+json";
+
+/// A DID resolution result.
+///
+/// <https://w3c-ccg.github.io/did-resolution/#did-resolution-result>
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionResult {
+    #[serde(rename = "@context")]
+    pub context: &'static str,
+    pub did_document: Option<CoreDocument>,
+    pub did_resolution_metadata: ResolutionMetadata,
+    pub did_document_metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ResolutionResult {
+    const CONTEXT: &'static str = "https://w3id.org/did-resolution/v1";
+
+    pub fn success(document: CoreDocument, content_type: &'static str, duration: u128) -> Self {
+        Self {
+            context: Self::CONTEXT,
+            did_document: Some(document),
+            did_resolution_metadata: ResolutionMetadata {
+                content_type: Some(content_type),
+                error: None,
+                error_message: None,
+                duration,
+            },
+            did_document_metadata: serde_json::Map::new(),
+        }
+    }
+
+    pub fn error(error: ResolutionErrorCode, message: String, duration: u128) -> Self {
+        Self {
+            context: Self::CONTEXT,
+            did_document: None,
+            did_resolution_metadata: ResolutionMetadata {
+                content_type: None,
+                error: Some(error),
+                error_message: Some(message),
+                duration,
+            },
+            did_document_metadata: serde_json::Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResolutionErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    /// The time resolution took in milliseconds.
+    pub duration: u128,
+}
+
+/// The error codes of DID resolution.
+///
+/// <https://www.w3.org/TR/did-spec-registries/#error>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResolutionErrorCode {
+    InvalidDid,
+    NotFound,
+    RepresentationNotSupported,
+    InternalError,
+}